@@ -1,34 +1,59 @@
+use std::{collections::HashMap, ffi::c_void};
+
 use polygen::{
-    items::types::{OpaquePtr, PolyBox},
+    __private::ExportedPolyStruct,
+    items::{
+        types::{OpaquePtr, PolyBox, PolyCallback, PolyHandle},
+        PolyType,
+    },
     polygen,
 };
 
+// the javascript glue is rendered from the layouts embedded in the wasm32 build
+polygen::export_bag!("Native");
+
+/// A struct with a readonly field.
 #[polygen]
 pub struct TestStruct {
-    x0: u32,
-    x1: u64,
+    /// The value read by `read`.
+    pub x0: u32,
+    #[polygen(readonly)]
+    pub x1: u64,
 }
 
 #[polygen]
 impl TestStruct {
+    /// The value of both fields after `new`.
+    pub const DEFAULT_VALUE: u32 = 42;
+
     pub fn new() -> Self {
-        Self { x0: 42, x1: 42 }
+        Self::new_with(Self::DEFAULT_VALUE)
     }
 
-    pub fn new_with(val: u32) -> Self {
+    /// Creates a struct with both fields set to `val`.
+    pub fn new_with(
+        /// The initial value of both fields.
+        val: u32,
+    ) -> Self {
         Self {
             x0: val,
             x1: val.into(),
         }
     }
 
+    #[polygen(trivial)]
     pub fn read(&self) -> u32 {
-        self.x0
+        *self.x0_ref()
+    }
+
+    // references can not be exported, so this is kept out of the bindings
+    #[polygen(skip)]
+    pub fn x0_ref(&self) -> &u32 {
+        &self.x0
     }
 
     pub fn modify(&mut self, val: u32) {
-        self.x0 = val;
-        self.x1 = val.into();
+        self.set_both(val);
     }
 
     pub fn duplicate(&self) -> Self {
@@ -45,9 +70,31 @@ impl TestStruct {
     }
 }
 
+// a block with only private helpers exports nothing
+#[polygen]
+impl TestStruct {
+    fn set_both(&mut self, val: u32) {
+        self.x0 = val;
+        self.x1 = val.into();
+    }
+}
+
 #[polygen]
 pub struct TestStruct2 {
-    nested: sub_module::TestStruct2,
+    pub nested: sub_module::TestStruct2,
+}
+
+/// The largest number of items a list can hold.
+#[polygen]
+pub const MAX_ITEMS: u32 = 64;
+
+#[polygen]
+pub static BUILD_NUMBER: u64 = 1024;
+
+#[polygen]
+pub struct LinkedItem {
+    pub value: u32,
+    pub next: *mut LinkedItem,
 }
 
 #[polygen]
@@ -55,13 +102,25 @@ pub fn pointer_test(_input: *mut TestStruct) -> *mut *const TestStruct2 {
     todo!()
 }
 
+#[polygen]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn next_value(item: *const LinkedItem) -> u32 {
+    unsafe { (*(*item).next).value }
+}
+
 #[polygen]
 pub fn execute(item: TestStruct2) {
     drop(item)
 }
 
-#[polygen]
-pub fn get_u32(item: TestStruct) -> u32 {
+/// Returns the first field of `item`.
+///
+/// Cheap enough to be called <b>very</b> often.
+#[polygen(trivial)]
+pub fn get_u32(
+    /// The struct to read from.
+    item: TestStruct,
+) -> u32 {
     item.x0
 }
 
@@ -77,11 +136,159 @@ pub fn create_ptr(val: u64) -> PolyBox<sub_module::TestStruct2> {
     })
 }
 
+#[polygen]
+pub fn box_nested(item: TestStruct2) -> PolyBox<TestStruct2> {
+    PolyBox::new(item)
+}
+
 #[polygen]
 pub fn change_item(mut item: PolyBox<sub_module::TestStruct2>, val: u64) {
     item.item.x1 = val
 }
 
+/// Immediately calls `cb` with an event id and the given user data.
+#[polygen]
+pub fn on_event(cb: extern "C" fn(u32, *mut c_void), user_data: *mut c_void) {
+    cb(7, user_data)
+}
+
+/// Receives messages logged by the library, possibly from multiple threads.
+#[polygen(export, trust_thread_safe)]
+pub trait Logger: Send + Sync {
+    /// Handles a single message.
+    fn log(
+        &self,
+        level: u32,
+        /// The item the message is about.
+        item: TestStruct,
+    );
+
+    /// Writes out buffered messages, returning false if that failed.
+    fn flush(&mut self) -> bool;
+}
+
+/// Logs `item` and flushes the logger, which is released afterwards.
+// javascript can not implement foreign traits, so this is kept out of the embedded bag
+#[polygen(manual)]
+pub fn log_item(mut logger: ForeignLogger, item: TestStruct) -> bool {
+    logger.log(1, item);
+    logger.flush()
+}
+
+/// Stores values by key, which is passed to foreign code as a handle.
+#[derive(Default)]
+pub struct Registry {
+    values: HashMap<u32, u64>,
+}
+
+unsafe impl ExportedPolyStruct for Registry {
+    type ExportedType = PolyHandle<Self>;
+    const STRUCT: PolyType = PolyHandle::<Self>::describe(
+        module_path!(),
+        "Registry",
+        "Stores values by key, which is passed to foreign code as a handle.",
+    );
+    const CONSUMES_HANDLE: bool = true;
+}
+
+impl From<PolyHandle<Registry>> for Registry {
+    fn from(value: PolyHandle<Registry>) -> Self {
+        value.into_inner()
+    }
+}
+
+#[polygen]
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: u32, value: u64) {
+        self.values.insert(key, value);
+    }
+
+    /// Returns the value stored for `key`, or zero.
+    pub fn get(&self, key: u32) -> u64 {
+        self.values.get(&key).copied().unwrap_or_default()
+    }
+
+    /// Consumes the registry and returns the sum of its values.
+    pub fn total(self) -> u64 {
+        self.values.values().sum()
+    }
+}
+
+/// A set of entities, which foreign code only accesses through its methods.
+#[polygen(opaque)]
+#[derive(Default)]
+pub struct World {
+    health: Vec<u32>,
+    names: HashMap<u32, String>,
+}
+
+#[polygen]
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entity and returns its id.
+    pub fn spawn(&mut self, health: u32) -> u32 {
+        let id = self.health.len() as u32;
+        self.health.push(health);
+        self.names.insert(id, format!("entity {id}"));
+        id
+    }
+
+    pub fn health(&self, id: u32) -> u32 {
+        self.health[id as usize]
+    }
+
+    /// Returns the number of entities.
+    #[polygen(include)]
+    fn len(&self) -> u32 {
+        self.health.len() as u32
+    }
+
+    /// Moves every entity of `other` into this world.
+    pub fn merge(&mut self, other: World) {
+        for health in other.health {
+            self.spawn(health);
+        }
+    }
+
+    /// Consumes the world and returns the health of all entities.
+    pub fn total_health(self) -> u64 {
+        self.health.iter().map(|h| *h as u64).sum()
+    }
+}
+
+/// Counts how often it is flushed and ignores every message.
+pub struct CountingLogger {
+    pub count: u32,
+}
+
+impl Logger for CountingLogger {
+    fn log(&self, _level: u32, _item: TestStruct) {}
+
+    fn flush(&mut self) -> bool {
+        self.count += 1;
+        self.count > 1
+    }
+}
+
+/// Creates a logger implemented by the library.
+#[polygen]
+pub fn create_logger() -> Box<dyn Logger> {
+    Box::new(CountingLogger { count: 0 })
+}
+
+/// Calls `cb` for every tick until it returns false, then releases it.
+#[polygen]
+pub fn run_ticks(cb: PolyCallback<(u32,), bool>, max: u32) -> u32 {
+    (0..max).find(|tick| !cb.call((*tick,))).unwrap_or(max)
+}
+
 pub mod sub_module {
     use polygen::polygen;
 
@@ -92,6 +299,31 @@ pub mod sub_module {
         pub(crate) item: TestStruct,
     }
 
+    #[polygen]
+    pub const SCALE: f64 = 0.5;
+
+    #[polygen]
+    #[derive(Clone)]
+    pub struct Settings {
+        pub volume: f32,
+        pub muted: bool,
+    }
+
+    #[polygen]
+    pub static DEFAULT_SETTINGS: Settings = Settings {
+        volume: 1.0,
+        muted: false,
+    };
+
+    // a struct can have more than one impl block, even in another module
+    #[polygen]
+    impl TestStruct {
+        pub fn reset(&mut self) {
+            self.x0 = 0;
+            self.x1 = 0;
+        }
+    }
+
     #[polygen]
     pub fn sub_module_function(item: TestStruct) -> u32 {
         item.x0
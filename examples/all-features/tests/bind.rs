@@ -1,13 +1,1386 @@
-use std::{fs, path::PathBuf};
+use std::{
+    env,
+    ffi::c_void,
+    fs,
+    io::Write,
+    mem::{align_of, offset_of, size_of},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::Arc,
+};
 
 use all_features::{
-    change_item, create_opaque, create_ptr, execute, get_u32, pointer_test, sub_module, TestStruct,
+    box_nested, change_item, create_logger, create_opaque, create_ptr, execute, get_u32, log_item,
+    next_value, on_event, pointer_test, run_ticks, sub_module, CountingLogger, ForeignLogger,
+    Logger, LoggerVTable, Registry, TestStruct, TestStruct2, World, BUILD_NUMBER, MAX_ITEMS,
+};
+use polygen::{
+    __private::ExportedPolyStruct,
+    diff::{BagDiff, Change, ChangeKind, Severity},
+    ir::{FieldType, ItemKey, PolyType, PolyValue, StructKind},
+    items::{
+        self,
+        types::{DynHandle, OpaquePtr, PolyCallback, PolyHandle},
+    },
+    polygen,
+    validate::ProblemKind,
+    PolyBag, PolyMod,
 };
-use polygen::PolyBag;
-use polygen_csharp::CSharpRenderer;
+use polygen_csharp::{CSharpError, CSharpRenderer, ImportStyle};
+use polygen_js::{JsError, JsRenderer};
+use polygen_php::{PhpError, PhpRenderer};
 
 static OUTPUT_DIR: &str = "target/polygen";
 
+fn create_bag() -> PolyBag {
+    create_js_bag().register_function::<log_item>()
+}
+
+/// Every item except the traits implemented by foreign code, which javascript does not support.
+fn create_js_bag() -> PolyBag {
+    PolyBag::new("Native")
+        .register_impl::<TestStruct>()
+        .register_function::<pointer_test>()
+        .register_function::<next_value>()
+        .register_function::<execute>()
+        .register_function::<get_u32>()
+        .register_function::<create_opaque>()
+        .register_function::<create_ptr>()
+        .register_function::<box_nested>()
+        .register_function::<change_item>()
+        .register_function::<on_event>()
+        .register_function::<run_ticks>()
+        .register_impl::<Box<dyn Logger>>()
+        .register_function::<create_logger>()
+        .register_impl::<Registry>()
+        .register_impl::<World>()
+        .register_function::<sub_module::sub_module_function>()
+        .register_struct::<sub_module::Settings>()
+        .register_const::<MAX_ITEMS>()
+        .register_const::<sub_module::SCALE>()
+        .register_function::<BUILD_NUMBER>()
+        .register_function::<sub_module::DEFAULT_SETTINGS>()
+}
+
+fn collect_struct_keys(m: &PolyMod, keys: &mut Vec<ItemKey>) {
+    keys.extend(m.structs().map(|(s, _)| s.key()));
+    for m in m.modules() {
+        collect_struct_keys(m, keys);
+    }
+}
+
+fn collect_export_names(m: &PolyMod, names: &mut Vec<String>) {
+    for (_, i) in m.structs() {
+        names.extend(
+            i.iter()
+                .flat_map(|i| &i.functions)
+                .map(|f| f.export_name.clone()),
+        );
+    }
+
+    names.extend(m.functions().map(|f| f.export_name.clone()));
+    for m in m.modules() {
+        collect_export_names(m, names);
+    }
+}
+
+#[test]
+fn item_identity() {
+    let bag = create_bag();
+    let mut keys = Vec::new();
+    collect_struct_keys(bag.root_module(), &mut keys);
+    let names = keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    // same named structs in different modules are kept apart
+    assert!(names.contains(&"all_features::TestStruct2".to_string()));
+    assert!(names.contains(&"all_features::sub_module::TestStruct2".to_string()));
+
+    // every generic instance is registered
+    assert!(names.contains(&"polygen::PolyBox<all_features::TestStruct2>".to_string()));
+    assert!(names.contains(&"polygen::PolyBox<all_features::sub_module::TestStruct2>".to_string()));
+
+    let mut unique = keys.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), keys.len());
+
+    // equality and hashing follow the key
+    let root = bag
+        .root_module()
+        .structs()
+        .find(|(s, _)| s.name == "TestStruct2");
+    let sub = bag
+        .root_module()
+        .modules()
+        .find(|m| m.name() == "sub_module");
+    let sub = sub
+        .unwrap()
+        .structs()
+        .find(|(s, _)| s.name == "TestStruct2");
+    let (root, sub) = (root.unwrap().0, sub.unwrap().0);
+    assert_ne!(root, sub);
+    assert_eq!(root.key().krate, sub.key().krate);
+    assert_eq!(sub.key().module, "sub_module");
+}
+
+#[test]
+fn pointer_discovery() {
+    // structs only reachable through pointers are registered
+    let bag = PolyBag::new("Native").register_function::<pointer_test>();
+    let mut keys = Vec::new();
+    collect_struct_keys(bag.root_module(), &mut keys);
+    let names = keys.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "all_features::TestStruct",
+            "all_features::TestStruct2",
+            "all_features::sub_module::TestStruct2",
+        ]
+    );
+
+    // recursive structs are registered once with all of their fields
+    let bag = PolyBag::new("Native").register_function::<next_value>();
+    let structs = bag.root_module().structs().collect::<Vec<_>>();
+    assert_eq!(structs.len(), 1);
+    assert_eq!(structs[0].0.name, "LinkedItem");
+    assert_eq!(structs[0].0.fields.len(), 2);
+    assert_eq!(bag.validate(), vec![]);
+}
+
+#[test]
+fn register_items() {
+    // structs and constants can be registered without being used by a function
+    let bag = PolyBag::new("Native")
+        .register_struct::<sub_module::Settings>()
+        .register_const::<MAX_ITEMS>();
+    let sub = bag
+        .root_module()
+        .modules()
+        .find(|m| m.name() == "sub_module");
+    let settings = sub.unwrap().structs().next().unwrap().0;
+    assert_eq!(settings.name, "Settings");
+
+    let max_items = bag.root_module().constants().next().unwrap();
+    assert_eq!(max_items.name, "MAX_ITEMS");
+    assert_eq!(max_items.value, PolyValue::UInt(64));
+
+    // modules pull in everything annotated inside of them
+    let bag = polygen::register_module!(PolyBag::new("Native"), all_features::sub_module);
+    assert!(bag.root_module().functions().next().is_none());
+    assert!(bag.root_module().constants().next().is_none());
+
+    let sub = bag
+        .root_module()
+        .modules()
+        .find(|m| m.name() == "sub_module");
+    let sub = sub.unwrap();
+    let structs = sub
+        .structs()
+        .map(|(s, _)| s.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(structs, ["TestStruct2", "Settings"]);
+    let functions = sub.functions().map(|f| f.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(functions, ["DEFAULT_SETTINGS", "sub_module_function"]);
+    let constants = sub.constants().map(|c| c.value).collect::<Vec<_>>();
+    assert_eq!(constants, [PolyValue::Float(0.5)]);
+
+    // statics are exported as an accessor function
+    let bag = PolyBag::new("Native").register_function::<BUILD_NUMBER>();
+    let accessor = bag.root_module().functions().next().unwrap();
+    assert_eq!(accessor.name, "BUILD_NUMBER");
+    assert!(accessor
+        .export_name
+        .starts_with("__polygen_static_BUILD_NUMBER_"));
+    assert!(accessor.params.inputs.is_empty());
+    assert!(accessor.params.output.is_some());
+}
+
+#[test]
+fn impl_blocks() {
+    // every impl block of a struct is merged into one impl, and the block of private helpers exports nothing
+    let bag = PolyBag::new("Native").register_impl::<TestStruct>();
+    let (_, i) = bag.root_module().structs().next().unwrap();
+    let methods = i.unwrap().functions.iter().map(|f| f.name.as_str());
+    let methods = methods.collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        [
+            "new",
+            "new_with",
+            "read",
+            "modify",
+            "duplicate",
+            "convert",
+            "reset"
+        ]
+    );
+
+    // registering the blocks again does not duplicate them
+    let again = polygen::register_module!(bag, all_features::sub_module);
+    let (_, i) = again.root_module().structs().next().unwrap();
+    assert_eq!(i.unwrap().functions.len(), 7);
+
+    // methods with the same name in different blocks are reported
+    let json = create_bag().to_json().unwrap();
+    let json = json.replace("\"name\": \"reset\"", "\"name\": \"modify\"");
+    let problems = PolyBag::from_json(&json).unwrap().validate();
+    assert!(problems
+        .iter()
+        .any(|p| { p.item == "TestStruct::modify" && p.kind == ProblemKind::DuplicateMethod }));
+}
+
+/// A counter whose methods are defined again by `CounterView`.
+#[polygen(opaque)]
+struct Counter {
+    value: u32,
+}
+
+#[polygen]
+impl Counter {
+    pub const LIMIT: u32 = 10;
+
+    pub fn new(value: u32) -> Self {
+        Self { value }
+    }
+
+    pub fn get(&self) -> u32 {
+        self.value
+    }
+}
+
+/// Exported as the same struct as `Counter`, so its impl block is merged into the one of `Counter`.
+struct CounterView {
+    value: u32,
+}
+
+unsafe impl ExportedPolyStruct for CounterView {
+    type ExportedType = PolyHandle<Self>;
+    const STRUCT: items::PolyType = PolyHandle::<Self>::describe(module_path!(), "Counter", "");
+}
+
+impl From<PolyHandle<CounterView>> for CounterView {
+    fn from(value: PolyHandle<CounterView>) -> Self {
+        value.into_inner()
+    }
+}
+
+#[polygen]
+impl CounterView {
+    pub const LIMIT: u32 = 20;
+
+    pub fn new(value: u32) -> Self {
+        Self { value }
+    }
+
+    pub fn get(&self) -> u32 {
+        self.value
+    }
+}
+
+#[test]
+fn duplicate_methods() {
+    // registering the blocks again is harmless, but methods sharing a name are all kept
+    let bag = PolyBag::new("Native")
+        .register_impl::<Counter>()
+        .register_impl::<CounterView>();
+    let (counter, i) = bag.root_module().structs().next().unwrap();
+    assert_eq!(counter.name, "Counter");
+    let i = i.unwrap();
+    let names = i
+        .functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["new", "get", "new", "get"]);
+    let values = i.constants.iter().map(|c| c.value).collect::<Vec<_>>();
+    assert_eq!(values, [PolyValue::UInt(10), PolyValue::UInt(20)]);
+
+    let problems = bag.validate();
+    for method in ["Counter::new", "Counter::get"] {
+        assert!(problems
+            .iter()
+            .any(|p| p.item == method && p.kind == ProblemKind::DuplicateMethod));
+    }
+    assert!(problems.iter().any(|p| {
+        p.item == "Counter::LIMIT" && matches!(p.kind, ProblemKind::NameCollision { .. })
+    }));
+}
+
+#[test]
+fn impl_items() {
+    // private and skipped methods are not exported
+    let bag = PolyBag::new("Native").register_impl::<TestStruct>();
+    let (_, i) = bag.root_module().structs().next().unwrap();
+    let i = i.unwrap();
+    assert!(!i
+        .functions
+        .iter()
+        .any(|f| f.name == "x0_ref" || f.name == "set_both"));
+
+    // associated consts become constants of the struct
+    let constant = &i.constants[0];
+    assert_eq!(constant.name, "DEFAULT_VALUE");
+    assert_eq!(constant.docs, "The value of both fields after `new`.");
+    assert_eq!(constant.value, PolyValue::UInt(42));
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp.contains("public const uint DefaultValue = 42;"));
+    assert!(JsRenderer
+        .render(&bag)
+        .unwrap()
+        .contains("static DEFAULT_VALUE = 42;"));
+}
+
+extern "C" fn count_tick(tick: u32, user_data: *mut c_void) -> bool {
+    unsafe { *(user_data as *mut u32) += 1 };
+    tick < 2
+}
+
+extern "C" fn release_ticks(user_data: *mut c_void) {
+    unsafe { *(user_data as *mut u32) = u32::MAX };
+}
+
+#[test]
+fn callbacks() {
+    // the user data is passed to every call and released by the destructor
+    let mut calls = 0u32;
+    let user_data = &mut calls as *mut u32 as *mut c_void;
+    let callback = PolyCallback::<(u32,), bool>::new(count_tick, user_data, Some(release_ticks));
+    assert!(callback.call((0,)));
+    assert!(!callback.call((5,)));
+    assert_eq!(unsafe { *(user_data as *mut u32) }, 2);
+    drop(callback);
+    assert_eq!(calls, u32::MAX);
+
+    let bag = PolyBag::new("Native")
+        .register_function::<on_event>()
+        .register_function::<run_ticks>();
+    assert_eq!(bag.validate(), vec![]);
+
+    let on_event = bag.root_module().functions().find(|f| f.name == "on_event");
+    let PolyType::FnPtr(cb) = &on_event.unwrap().params.inputs[0].ty else {
+        panic!("function pointers are exported as FnPtr");
+    };
+    let inputs = cb.inputs.iter().map(|t| t.key().to_string());
+    assert_eq!(inputs.collect::<Vec<_>>(), ["u32", "*c_void"]);
+    assert!(cb.output.is_none());
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::LibraryImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp
+        .contains("OnEvent(delegate* unmanaged[Cdecl]<uint, void*, void> cb, void* userData)"));
+    assert!(csharp
+        .contains("public unsafe delegate bool FnU32CVoidPtrReturnsBool(uint arg0, void* arg1);"));
+    assert!(
+        csharp.contains("RunTicks(Polygen.PolyCallback<FnU32CVoidPtrReturnsBool> cb, uint max)")
+    );
+    assert!(csharp.contains("var handle = GCHandle.Alloc(callback);"));
+
+    let php = PhpRenderer {
+        namespace: "AllFeatures".to_string(),
+        lib_name: "all_features".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(php.contains("typedef void (*Fn_u32_c_voidPtr)(uint32_t, void*);"));
+    assert!(php.contains("typedef bool (*Fn_u32_c_voidPtr_Returns_bool)(uint32_t, void*);"));
+}
+
+extern "C" fn log_into(
+    user_data: *mut c_void,
+    level: u32,
+    _item: <TestStruct as polygen::__private::ExportedPolyStruct>::ExportedType,
+) {
+    unsafe { *(user_data as *mut u32) += level };
+}
+
+extern "C" fn flush_into(user_data: *mut c_void) -> bool {
+    unsafe { *(user_data as *mut u32) *= 10 };
+    true
+}
+
+extern "C" fn release_into(user_data: *mut c_void) {
+    unsafe { *(user_data as *mut u32) += 1 };
+}
+
+#[test]
+fn traits() {
+    // the foreign implementation calls through the vtable and releases the user data on drop
+    let mut state = 0u32;
+    let mut logger = ForeignLogger::new(LoggerVTable {
+        user_data: &mut state as *mut u32 as *mut c_void,
+        log: log_into,
+        flush: flush_into,
+        drop: Some(release_into),
+    });
+    logger.log(3, TestStruct::new());
+    assert!(logger.flush());
+    drop(logger);
+    assert_eq!(state, 31);
+
+    // `trust_thread_safe` lets foreign implementations satisfy `Logger: Send + Sync`
+    fn thread_safe<T: Send + Sync>() {}
+    thread_safe::<ForeignLogger>();
+
+    let bag = PolyBag::new("Native").register_function::<log_item>();
+    assert_eq!(bag.validate(), vec![]);
+    let (vtable, _) = bag
+        .root_module()
+        .structs()
+        .find(|(s, _)| s.name == "Logger")
+        .unwrap();
+    let StructKind::VTable(methods) = &vtable.kind else {
+        panic!("traits are exported as vtables");
+    };
+    let names = methods.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["log", "flush"]);
+    let fields = vtable
+        .fields
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(fields, ["user_data", "log", "flush", "drop"]);
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp.contains("public interface ILogger"));
+    assert!(csharp.contains("    void Log(uint level, TestStruct item);"));
+    assert!(csharp.contains("public Logger(ILogger target)"));
+    assert!(csharp.contains(
+        "private static readonly LogFn log = (userData, level, item) => Target(userData).Log(level, new TestStruct(item));"
+    ));
+
+    let php = PhpRenderer {
+        namespace: "AllFeatures".to_string(),
+        lib_name: "all_features".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(php.contains("interface LoggerInterface"));
+    assert!(php.contains("public static function fromObject(LoggerInterface $target): self"));
+    assert!(php.contains("$self->flush = fn($userData) => $target->flush();"));
+
+    // javascript can not fill the function table of the library
+    let error = JsRenderer.render(&bag).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`all_features::Logger` is a trait implemented by foreign code, which is not supported by javascript"
+    );
+}
+
+#[test]
+fn dyn_traits() {
+    // boxed objects are moved out of their handle, shared objects stay in it
+    let mut handle = DynHandle::from(create_logger());
+    assert!(!unsafe { handle.object_mut() }.flush());
+    let mut boxed: Box<dyn Logger> = handle.into();
+    assert!(boxed.flush());
+
+    let logger: Arc<dyn Logger> = Arc::new(CountingLogger { count: 0 });
+    let shared: Arc<dyn Logger> = DynHandle::from(logger).into();
+    assert_eq!(Arc::strong_count(&shared), 2);
+
+    let bag = PolyBag::new("Native")
+        .register_impl::<Box<dyn Logger>>()
+        .register_function::<create_logger>();
+    assert_eq!(bag.validate(), vec![]);
+    let (handle, polyimpl) = bag
+        .root_module()
+        .structs()
+        .find(|(s, _)| s.name == "DynLogger")
+        .unwrap();
+    let StructKind::Handle(Some(interface)) = &handle.kind else {
+        panic!("trait objects are exported as handles of their trait");
+    };
+    assert!(matches!(interface.as_ref(), PolyType::Struct(s) if s.name == "Logger"));
+    let names = polyimpl
+        .unwrap()
+        .functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["log", "flush"]);
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp.contains("public interface ILogger"));
+    assert!(csharp.contains("public sealed class DynLogger : SafeHandle, ILogger"));
+    assert!(csharp.contains("public bool Flush()"));
+    assert!(csharp.contains("this.DangerousAddRef(ref __polygen_this_added);"));
+
+    let php = PhpRenderer {
+        namespace: "AllFeatures".to_string(),
+        lib_name: "all_features".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(php.contains("class DynLogger implements \\AllFeatures\\Native\\LoggerInterface"));
+
+    // javascript has no interfaces, so only the handle is rendered
+    let js = JsRenderer.render(&bag).unwrap();
+    assert!(js.contains("class DynLogger {"));
+    assert!(js.contains("    flush() {\n        return call(\"__polygen_dynfn_Logger_flush_"));
+    assert!(!js.contains("class Logger"));
+}
+
+#[test]
+fn typed_handles() {
+    let mut handle = PolyHandle::new(Registry::new());
+    handle.insert(1, 10);
+    handle.insert(2, 32);
+    assert_eq!(handle.get(2), 32);
+    assert_eq!(handle.into_inner().total(), 42);
+
+    let bag = PolyBag::new("Native").register_impl::<Registry>();
+    assert_eq!(bag.validate(), vec![]);
+    let (registry, polyimpl) = bag.root_module().structs().next().unwrap();
+    assert_eq!(registry.name, "Registry");
+    assert!(matches!(registry.kind, StructKind::Handle(None)));
+
+    // methods on a handle receive a pointer to the handle
+    let get = polyimpl.unwrap().functions.iter().find(|f| f.name == "get");
+    let self_ty = &get.unwrap().params.inputs[0].ty;
+    assert!(matches!(self_ty, PolyType::Pointer(t) if **t == PolyType::Struct(registry.clone())));
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp.contains("public sealed class Registry : SafeHandle"));
+    assert!(csharp.contains("public static Registry New() => new Registry("));
+    assert!(csharp.contains("public unsafe ulong Get(uint key)"));
+    assert!(csharp.contains("fixed (Data* __polygen_self_ptr = &_data)"));
+    assert!(csharp.contains("this.DangerousAddRef(ref __polygen_this_added);"));
+}
+
+#[test]
+fn consumed_handles() {
+    // the value is gone once a call took it by value, so native code panics on any later use
+    // foreign code passes a copy of the handle it keeps
+    let handle = PolyHandle::new(Registry::new());
+    let moved = unsafe { std::ptr::read(&handle) };
+    assert_eq!(moved.into_inner().total(), 0);
+    let reused = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handle.get(1)));
+    assert!(reused.is_err());
+
+    let bag = PolyBag::new("Native").register_impl::<Registry>();
+    let (_, polyimpl) = bag.root_module().structs().next().unwrap();
+    let functions = &polyimpl.unwrap().functions;
+    let consumed = |name: &str| {
+        let function = functions.iter().find(|f| f.name == name).unwrap();
+        function.params.inputs[0].consumed
+    };
+    assert!(consumed("total"));
+    assert!(!consumed("get"));
+    assert!(!consumed("insert"));
+
+    // so the bindings dispose the handle, and using it again throws before reaching native code
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    let total = &csharp[csharp.find("public ulong Total()").unwrap()..];
+    let total = &total[..total.find("public unsafe struct Data").unwrap()];
+    assert!(total.contains("this.Dispose();"));
+    let get = &csharp[csharp.find("public unsafe ulong Get(uint key)").unwrap()..];
+    let get = &get[..get.find("public ulong Total()").unwrap()];
+    assert!(!get.contains("Dispose()"));
+}
+
+#[test]
+fn opaque_into_inner() {
+    #[repr(C)]
+    struct ForeignOpaque {
+        id: u64,
+        ptr: *mut c_void,
+        drop: unsafe extern "C" fn(*mut c_void),
+    }
+
+    // foreign code keeps its copy of the pointer and still releases it after the value was moved out
+    let opaque = OpaquePtr::new(TestStruct { x0: 7, x1: 42 });
+    let foreign: ForeignOpaque = unsafe { std::mem::transmute_copy(&opaque) };
+    let moved = unsafe { std::ptr::read(&opaque) };
+    assert_eq!(moved.into_inner::<TestStruct>().unwrap().x0, 7);
+    assert!(opaque.as_ref::<TestStruct>().is_err());
+    unsafe { __polygen_drop(foreign.ptr, foreign.drop) };
+}
+
+#[test]
+fn opaque_structs() {
+    let mut world = PolyHandle::from(World::new());
+    let mut other = World::new();
+    assert_eq!(other.spawn(5), 0);
+    assert_eq!(world.spawn(10), 0);
+    world.merge(other);
+    assert_eq!(world.health(1), 5);
+    assert_eq!(World::from(world).total_health(), 15);
+
+    let bag = PolyBag::new("Native").register_impl::<World>();
+    assert_eq!(bag.validate(), vec![]);
+    let (world, polyimpl) = bag.root_module().structs().next().unwrap();
+    assert_eq!(world.name, "World");
+    assert_eq!(
+        world.docs,
+        "A set of entities, which foreign code only accesses through its methods."
+    );
+    assert!(matches!(world.kind, StructKind::Handle(None)));
+    let fields = world
+        .fields
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(fields, ["ptr", "drop"]);
+
+    // `include` exports methods that are not public
+    let functions = &polyimpl.unwrap().functions;
+    let names = functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["new", "spawn", "health", "len", "merge", "total_health"]
+    );
+
+    // other worlds are passed as handles, which are moved out of
+    let merge = polyimpl
+        .unwrap()
+        .functions
+        .iter()
+        .find(|f| f.name == "merge");
+    let inputs = &merge.unwrap().params.inputs;
+    assert_eq!(inputs[1].ty, PolyType::Struct(world.clone()));
+    assert!(!inputs[0].consumed);
+    assert!(inputs[1].consumed);
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp.contains("public sealed class World : SafeHandle"));
+    assert!(csharp.contains("public unsafe uint Spawn(uint health)"));
+    assert!(csharp.contains("other.DangerousAddRef(ref __polygen_other_added);"));
+
+    let php = PhpRenderer {
+        namespace: "AllFeatures".to_string(),
+        lib_name: "all_features".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(php.contains("void __polygen_drop(uintptr_t ptr, void (*destructor)(void *));"));
+    assert!(php.contains("public function merge(\\AllFeatures\\Native\\World $other): void"));
+    assert!(php.contains("public function release(): void"));
+
+    // released or moved handles throw instead of passing a dangling pointer to native code
+    let guard = "if ($this->ptr === 0) {\n                throw new \\LogicException('The handle was released or moved into a native call');";
+    let to_c = &php[php.find("public function toC()").unwrap()..];
+    assert!(to_c.starts_with(&format!(
+        "public function toC(): \\FFI\\CData\n        {{\n            {guard}"
+    )));
+    let release = &php[php.find("public function release(): void").unwrap()..];
+    assert!(release.contains(guard));
+    assert!(release.contains("$this->ptr = 0;"));
+    let merge = &php[php.find("public function merge(").unwrap()..];
+    let merge = &merge[..merge.find("public function totalHealth()").unwrap()];
+    assert!(merge.contains(
+        "$other->toC());\n            $this->assign($self);\n            $other->release();"
+    ));
+    let total = &php[php.find("public function totalHealth()").unwrap()..];
+    assert!(total
+        .contains("$this->toC());\n            $this->release();\n            return $result;"));
+
+    let js = JsRenderer.render(&bag).unwrap();
+    assert!(js.contains("exports().__polygen_drop(this._ptr, this._drop);\n        this._ptr = 0;"));
+    assert!(js.contains("if (!value._ptr) {"));
+    assert!(js.contains("[this, other]);\n        other.release();\n        return $result;"));
+}
+
+#[test]
+fn docs() {
+    let bag = create_bag();
+    let test_struct = bag.root_module().structs().next().unwrap();
+    assert_eq!(test_struct.0.docs, "A struct with a readonly field.");
+    assert_eq!(test_struct.0.fields[0].docs, "The value read by `read`.");
+    assert_eq!(test_struct.0.fields[1].docs, "");
+
+    let new_with = &test_struct.1.unwrap().functions[1];
+    assert_eq!(
+        new_with.docs,
+        "Creates a struct with both fields set to `val`."
+    );
+    assert_eq!(
+        new_with.params.inputs[0].docs,
+        "The initial value of both fields."
+    );
+
+    let get_u32 = bag.root_module().functions().find(|f| f.name == "get_u32");
+    assert_eq!(
+        get_u32.unwrap().docs,
+        "Returns the first field of `item`.\n\nCheap enough to be called <b>very</b> often."
+    );
+
+    // xml docs escape the doc comments
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(csharp.contains("/// Cheap enough to be called &lt;b&gt;very&lt;/b&gt; often."));
+    assert!(csharp.contains("/// <param name=\"item\">The struct to read from.</param>"));
+
+    let php = PhpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+    assert!(php.contains(" * @param int $val The initial value of both fields."));
+
+    let js = JsRenderer.render(&create_js_bag()).unwrap();
+    assert!(js.contains(" * @property {number} x0 The value read by `read`."));
+}
+
+#[test]
+fn json_round_trip() {
+    let bag = create_bag();
+    let json = bag.to_json().unwrap();
+    let loaded = PolyBag::from_json(&json).unwrap();
+    assert_eq!(loaded.to_json().unwrap(), json);
+
+    // generators must not be able to tell the difference
+    let renderer = PhpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+    };
+    assert_eq!(
+        renderer.render(&loaded).unwrap(),
+        renderer.render(&bag).unwrap()
+    );
+
+    // documents with a different schema version are rejected
+    let outdated = json.replacen(
+        &format!("\"version\": {}", polygen::ir::SCHEMA_VERSION),
+        "\"version\": 0",
+        1,
+    );
+    assert!(matches!(
+        PolyBag::from_json(&outdated),
+        Err(polygen::ir::IrError::Version(0))
+    ));
+}
+
+#[test]
+fn diff() {
+    let bag = create_bag();
+    assert!(BagDiff::new(&bag, &bag).changes.is_empty());
+
+    // adding a function is compatible, removing it breaks existing bindings
+    let old = PolyBag::new("Native").register_impl::<TestStruct>();
+    let added = BagDiff::new(&old, &bag);
+    assert_eq!(added.severity(), Severity::Compatible);
+    assert!(added.check(Severity::Compatible).is_ok());
+
+    let removed = BagDiff::new(&bag, &old);
+    assert_eq!(removed.severity(), Severity::AbiBreaking);
+    assert!(removed.changes.iter().any(|c| {
+        c.path == "all_features::change_item" && c.kind == ChangeKind::FunctionRemoved
+    }));
+    assert!(removed.check(Severity::SourceBreaking).is_err());
+
+    // making a field readonly only breaks code using the bindings
+    let json = bag.to_json().unwrap();
+    let writable = PolyBag::from_json(&json.replace("\"readonly\": true", "\"readonly\": false"));
+    let readonly = BagDiff::new(&writable.unwrap(), &bag);
+    assert_eq!(readonly.severity(), Severity::SourceBreaking);
+    assert!(readonly.check(Severity::SourceBreaking).is_ok());
+
+    // existing bindings still contain the old value of a constant
+    let changed = PolyBag::from_json(&json.replace("\"uint\": 64", "\"uint\": 32"));
+    let changed = BagDiff::new(&changed.unwrap(), &bag);
+    assert_eq!(
+        changed.changes[0].kind,
+        ChangeKind::ConstantValueChanged {
+            old: "32".to_string(),
+            new: "64".to_string(),
+        }
+    );
+    assert_eq!(changed.severity(), Severity::AbiBreaking);
+
+    // existing bindings look functions up by their export name
+    let get_u32 = bag.root_module().functions().find(|f| f.name == "get_u32");
+    let export_name = &get_u32.unwrap().export_name;
+    let renamed = json.replace(export_name, "__polygen_fn_get_u32_renamed");
+    let renamed = BagDiff::new(&PolyBag::from_json(&renamed).unwrap(), &bag);
+    assert_eq!(
+        renamed.changes,
+        vec![Change {
+            path: "all_features::get_u32".to_string(),
+            kind: ChangeKind::ExportNameChanged {
+                old: "__polygen_fn_get_u32_renamed".to_string(),
+                new: export_name.clone(),
+            },
+        }]
+    );
+    assert_eq!(renamed.severity(), Severity::AbiBreaking);
+
+    // a handle is passed as a pointer instead of the fields of a struct
+    let handles = json.replace("\"kind\": \"data\"", "\"kind\": { \"handle\": null }");
+    let handles = BagDiff::new(&PolyBag::from_json(&handles).unwrap(), &bag);
+    assert!(handles.changes.contains(&Change {
+        path: "all_features::TestStruct".to_string(),
+        kind: ChangeKind::KindChanged {
+            old: "handle".to_string(),
+            new: "data".to_string(),
+        },
+    }));
+    assert_eq!(handles.severity(), Severity::AbiBreaking);
+}
+
+extern "C" {
+    fn __polygen_abi_fingerprint(name: *const u8, len: usize) -> u64;
+    fn __polygen_drop(ptr: *mut c_void, destructor: unsafe extern "C" fn(*mut c_void));
+}
+
+#[test]
+fn abi_fingerprints() {
+    let bag = create_bag();
+    let get_u32 = bag.root_module().functions().find(|f| f.name == "get_u32");
+    let get_u32 = get_u32.unwrap();
+    let name = get_u32.export_name.as_bytes();
+
+    // the fingerprints are collected once and then looked up by name
+    let fingerprint = unsafe { __polygen_abi_fingerprint(name.as_ptr(), name.len()) };
+    assert_eq!(fingerprint, get_u32.params.fingerprint());
+    let again = unsafe { __polygen_abi_fingerprint(name.as_ptr(), name.len()) };
+    assert_eq!(again, fingerprint);
+
+    let unknown = b"__polygen_fn_unknown";
+    assert_eq!(
+        unsafe { __polygen_abi_fingerprint(unknown.as_ptr(), unknown.len()) },
+        0
+    );
+    assert_eq!(unsafe { __polygen_abi_fingerprint(std::ptr::null(), 0) }, 0);
+
+    // registered structs are verified even if no function uses them
+    let fingerprints = bag.root_module().fingerprints();
+    let (name, settings) = fingerprints
+        .iter()
+        .find(|(name, _)| name == "all_features::sub_module::Settings")
+        .unwrap();
+    let name = name.as_bytes();
+    let fingerprint = unsafe { __polygen_abi_fingerprint(name.as_ptr(), name.len()) };
+    assert_eq!(fingerprint, *settings);
+}
+
+#[test]
+fn abi_fingerprint_layouts() {
+    let bag = create_bag();
+    let pointer_test = bag
+        .root_module()
+        .functions()
+        .find(|f| f.name == "pointer_test");
+    let params = pointer_test.unwrap().params.clone();
+
+    // a field changed behind a pointer changes the fingerprint of the function
+    let mut changed = params.clone();
+    let PolyType::Pointer(input) = &mut changed.inputs[0].ty else {
+        panic!("expected a pointer")
+    };
+    let PolyType::Struct(input) = input.as_mut() else {
+        panic!("expected a struct")
+    };
+    input.fields[1].offset += 8;
+    assert_ne!(changed.fingerprint(), params.fingerprint());
+
+    // and so does a field of a struct only reached through a pointer to a pointer
+    let mut changed = params.clone();
+    let PolyType::Pointer(output) = changed.output.as_mut().unwrap() else {
+        panic!("expected a pointer")
+    };
+    let PolyType::Pointer(output) = output.as_mut() else {
+        panic!("expected a pointer")
+    };
+    let PolyType::Struct(output) = output.as_mut() else {
+        panic!("expected a struct")
+    };
+    output.fields[0].name = "renamed".to_string();
+    assert_ne!(changed.fingerprint(), params.fingerprint());
+
+    let fingerprints = bag.root_module().fingerprints();
+    let fingerprint = |name: &str| fingerprints.iter().find(|(n, _)| n == name).unwrap().1;
+    let structs = bag.root_module().reachable_structs(|_| true);
+    let mut settings = structs.into_iter().find(|s| s.name == "Settings").unwrap();
+    assert_eq!(
+        settings.fingerprint(),
+        fingerprint("all_features::sub_module::Settings")
+    );
+    settings.size += 4;
+    assert_ne!(
+        settings.fingerprint(),
+        fingerprint("all_features::sub_module::Settings")
+    );
+}
+
+#[test]
+fn validate() {
+    let bag = create_bag();
+    assert_eq!(bag.validate(), vec![]);
+
+    let json = bag.to_json().unwrap();
+    let json = json
+        .replace("\"primitive\": \"u64\"", "\"primitive\": \"u128\"")
+        .replace("\"name\": \"val\"", "\"name\": \"object\"")
+        .replace("\"name\": \"new_with\"", "\"name\": \"NewWith\"")
+        .replace("\"name\": \"modify\"", "\"name\": \"new\"");
+    let problems = PolyBag::from_json(&json).unwrap().validate();
+    let kinds = problems.iter().map(|p| &p.kind).collect::<Vec<_>>();
+    assert!(kinds.contains(&&ProblemKind::UnsupportedType("u128".to_string())));
+    assert!(kinds.contains(&&ProblemKind::ReservedKeyword {
+        name: "object".to_string(),
+        lang: "C#",
+    }));
+
+    // `modify` was renamed to an existing method
+    assert!(problems.iter().any(|p| {
+        p.module == "all_features"
+            && p.item == "TestStruct::new"
+            && p.kind == ProblemKind::DuplicateMethod
+    }));
+
+    // php keywords can not name functions, classes or constants in any case, or variables as `$this`
+    let json = create_bag().to_json().unwrap();
+    let json = json
+        .replace("\"name\": \"get_u32\"", "\"name\": \"list\"")
+        .replace("\"name\": \"LinkedItem\"", "\"name\": \"Array\"")
+        .replace("\"name\": \"MAX_ITEMS\"", "\"name\": \"class\"")
+        .replace("\"name\": \"val\"", "\"name\": \"this\"");
+    let problems = PolyBag::from_json(&json).unwrap().validate();
+    for name in ["list", "Array", "CLASS", "this"] {
+        let name = name.to_string();
+        let kind = ProblemKind::ReservedKeyword { name, lang: "PHP" };
+        assert!(problems.iter().any(|p| p.kind == kind));
+    }
+
+    // generators report types they can not render when the bag was not validated
+    let json = create_js_bag().to_json().unwrap();
+    let json = json.replace("\"primitive\": \"u64\"", "\"primitive\": \"u128\"");
+    let bag = PolyBag::from_json(&json).unwrap();
+    assert!(matches!(
+        JsRenderer.render(&bag),
+        Err(JsError::UnsupportedPrimitive(p)) if p == "u128"
+    ));
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag);
+    assert!(matches!(csharp, Err(CSharpError::UnsupportedType(p)) if p == "u128"));
+    let php = PhpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+    }
+    .render(&bag);
+    assert!(matches!(php, Err(PhpError::UnsupportedType(p)) if p == "u128"));
+}
+
+#[test]
+fn php_bindings() {
+    let bag = create_bag();
+    let php = PhpRenderer {
+        namespace: "AllFeatures".to_string(),
+        lib_name: "all_features".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+
+    // modules become namespaces and structs become classes with typed properties
+    assert!(php.contains("namespace AllFeatures\\Native\\SubModule\n{\n    class TestStruct2\n"));
+    assert!(php.contains("        public int $x0;\n        public int $x1;\n"));
+    assert!(php.contains("        private \\AllFeatures\\Native\\TestStruct $item;\n"));
+
+    // methods delegate to the loaded ffi instance
+    assert!(
+        php.contains("public static function newWith(int $val): \\AllFeatures\\Native\\TestStruct")
+    );
+    assert!(php.contains("\\AllFeatures\\Native::ffi()->__polygen_implfn_new_with_"));
+
+    // every export is declared, and structs used by value are defined before their users
+    let cdef = php.split("<<<'CDEF'\n").nth(1).unwrap();
+    let cdef = cdef.split("CDEF;").next().unwrap();
+    let mut names = Vec::new();
+    collect_export_names(bag.root_module(), &mut names);
+    for name in names {
+        assert!(
+            cdef.contains(&format!(" {name}(")),
+            "`{name}` is not declared"
+        );
+    }
+
+    let position = |s: &str| cdef.find(s).unwrap();
+    assert!(position("struct TestStruct {") < position("struct SubModule_TestStruct2 {"));
+    assert!(position("struct SubModule_TestStruct2 {") < position("struct TestStruct2 {"));
+
+    // the declarations are checked by a C compiler when one is installed
+    let args = ["-fsyntax-only", "-x", "c", "-"];
+    let includes = ["stdint.h", "stdbool.h", "stddef.h"].map(|h| ["-include", h]);
+    let compiler = Command::new("cc")
+        .args(includes.concat())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    match compiler {
+        Ok(mut child) => {
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(cdef.as_bytes())
+                .unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Err(_) => eprintln!("skipping the C syntax check, no C compiler was found"),
+    }
+}
+
+#[test]
+fn import_styles() {
+    let bag = create_bag();
+    let (_, i) = bag.root_module().structs().next().unwrap();
+    let function = |name: &str| {
+        i.unwrap()
+            .functions
+            .iter()
+            .find(|f| f.name == name)
+            .unwrap()
+    };
+    assert!(function("read").trivial);
+    assert!(!function("new_with").trivial);
+
+    let render = |import_style| {
+        CSharpRenderer {
+            lib_name: "all_features".to_string(),
+            namespace: "AllFeatures".to_string(),
+            import_style,
+        }
+        .render(&bag)
+        .unwrap()
+    };
+
+    let csharp = render(ImportStyle::DllImport);
+    let imports = csharp
+        .matches("[DllImport(\"all_features\", CallingConvention = CallingConvention.Cdecl)]")
+        .count();
+    assert!(imports > 0);
+    assert_eq!(imports, csharp.matches(" static unsafe extern ").count());
+    assert!(!csharp.contains("LibraryImport") && !csharp.contains("DisableRuntimeMarshalling"));
+    assert!(csharp.contains("public static class Native\n"));
+
+    // every import is generated at compile time, and only trivial functions skip the gc transition
+    let csharp = render(ImportStyle::LibraryImport);
+    assert!(csharp.contains("\n[assembly: DisableRuntimeMarshalling]\n"));
+    assert!(!csharp.contains("DllImport"));
+    let imports = csharp
+        .matches("[LibraryImport(\"all_features\")]\n")
+        .count();
+    assert!(imports > 0);
+    assert_eq!(imports, csharp.matches(" static unsafe partial ").count());
+    assert!(csharp.contains("public static partial class Native\n"));
+
+    let read = &function("read").export_name;
+    let new_with = &function("new_with").export_name;
+    let declaration = |name: &str| {
+        let end = csharp.find(&format!(" {name}(")).unwrap();
+        let start = csharp[..end].rfind("[LibraryImport(").unwrap();
+        &csharp[start..end]
+    };
+    assert!(declaration(read).contains("[SuppressGCTransition]"));
+    assert!(!declaration(new_with).contains("[SuppressGCTransition]"));
+}
+
+#[test]
+fn csharp_properties() {
+    let bag = PolyBag::new("Native")
+        .register_impl::<TestStruct>()
+        .register_struct::<TestStruct2>();
+    let mut structs = bag.root_module().structs().map(|(s, _)| s);
+    let test_struct = structs.next().unwrap();
+    let flags = test_struct.fields.iter().map(|f| (f.visible, f.readonly));
+    assert_eq!(flags.collect::<Vec<_>>(), [(true, false), (true, true)]);
+
+    let test_struct2 = structs.find(|s| s.name == "TestStruct2").unwrap();
+    let FieldType::Typed(PolyType::Struct(nested)) = &test_struct2.fields[0].ty else {
+        panic!("`nested` is a struct");
+    };
+    assert!(!nested.fields[0].visible);
+
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+
+    // visible fields are properties, readonly fields only have a getter
+    assert!(csharp.contains("public uint X0 { get => _data.x0; set => _data.x0 = value; }"));
+    assert!(csharp.contains("public ulong X1 => _data.x1;"));
+    assert!(csharp.contains(
+        "public SubModule.TestStruct2 Nested { get => new SubModule.TestStruct2(_data.nested); \
+         set => _data.nested = value._data; }"
+    ));
+
+    // private fields stay inside of the internal data
+    assert!(csharp.contains("[FieldOffset(0)] internal TestStruct.Data item;"));
+    assert!(!csharp.contains(" Item "));
+}
+
+type ExportedTestStruct = <TestStruct as ExportedPolyStruct>::ExportedType;
+
+/// The `#[repr(C)]` layout `TestStruct` is expected to be exported with.
+#[repr(C)]
+struct TestStructLayout {
+    x0: u32,
+    x1: u64,
+}
+
+#[test]
+fn layout_metadata() {
+    let bag = create_bag();
+    let test_struct = bag.root_module().structs().next().unwrap().0;
+    assert_eq!(test_struct.size, size_of::<ExportedTestStruct>());
+    assert_eq!(test_struct.align, align_of::<ExportedTestStruct>());
+    assert_eq!(test_struct.size, size_of::<TestStructLayout>());
+    let offsets = test_struct.fields.iter().map(|f| f.offset);
+    assert_eq!(
+        offsets.collect::<Vec<_>>(),
+        [
+            offset_of!(TestStructLayout, x0),
+            offset_of!(TestStructLayout, x1)
+        ]
+    );
+
+    // builtin structs record their layout too
+    for s in bag.root_module().reachable_structs(|_| true) {
+        assert!(
+            s.align.is_power_of_two() && s.size % s.align == 0,
+            "{}",
+            s.name
+        );
+        let offsets = s.fields.iter().map(|f| f.offset).collect::<Vec<_>>();
+        assert!(offsets.windows(2).all(|w| w[0] < w[1]), "{}", s.name);
+        assert!(offsets.iter().all(|offset| *offset < s.size), "{}", s.name);
+    }
+
+    // c# uses the recorded offsets instead of its own layout rules
+    let csharp = CSharpRenderer {
+        lib_name: "all_features".to_string(),
+        namespace: "AllFeatures".to_string(),
+        import_style: ImportStyle::DllImport,
+    }
+    .render(&bag)
+    .unwrap();
+    let size = test_struct.size;
+    let x1 = test_struct.fields[1].offset;
+    assert!(csharp.contains(&format!(
+        "[StructLayout(LayoutKind.Explicit, Size = {size})]\n            public struct Data\n"
+    )));
+    assert!(csharp.contains(&format!("[FieldOffset({x1})] internal ulong x1;")));
+
+    // php checks the recorded layouts against the library through the struct fingerprints
+    let php = PhpRenderer {
+        namespace: "AllFeatures".to_string(),
+        lib_name: "all_features".to_string(),
+    }
+    .render(&bag)
+    .unwrap();
+    let fingerprint = test_struct.fingerprint() as i64;
+    assert!(php.contains(&format!("'all_features::TestStruct' => {fingerprint},")));
+    assert!(!php.contains("LAYOUTS"));
+}
+
+/// Prints the json of the bag embedded in the wasm32 build of the library.
+static JS_DUMP: &str = r#"
+import fs from "fs";
+
+const { instance } = await WebAssembly.instantiate(fs.readFileSync(process.argv[2]));
+const wasm = instance.exports;
+const len = wasm.__polygen_alloc(4, 4);
+const ptr = wasm.__polygen_bag_json(len);
+const bytes = new Uint8Array(wasm.memory.buffer, ptr, new DataView(wasm.memory.buffer).getUint32(len, true));
+process.stdout.write(new TextDecoder().decode(bytes));
+"#;
+
+/// Calls the javascript glue rendered from the embedded bag against the wasm32 build of the library.
+static JS_HARNESS: &str = r#"
+import fs from "fs";
+import { load, Native } from "./AllFeatures.mjs";
+
+await load(fs.readFileSync(process.argv[2]));
+
+function check(condition, message) {
+    if (!condition) {
+        throw new Error(message);
+    }
+}
+
+const item = Native.TestStruct.newWith(21);
+check(item.x0 === 21 && item.x1 === 21n, `newWith returned ${item.x0} and ${item.x1}`);
+
+item.modify(7);
+check(item.x0 === 7 && item.x1 === 7n, `modify left ${item.x0} and ${item.x1}`);
+check(Native.getU32(item) === 7, "getU32 did not read the struct");
+check(Native.SubModule.subModuleFunction(item) === 7, "the sub module function did not read the struct");
+check(Native.buildNumber() === 1024n, "the static was not read");
+check(Native.SubModule.defaultSettings().volume === 1, "the settings were not read");
+
+// handles hold a pointer and a destructor from the function table of the module
+const registry = Native.Registry.new();
+registry.insert(1, 5n);
+registry.insert(2, 6n);
+check(registry.get(1) === 5n, "the registry lost a value");
+check(registry.total() === 11n, "the registry did not sum its values");
+check(registry._ptr === 0, "the consumed registry was not released");
+
+const world = Native.World.new();
+world.spawn(10);
+world.spawn(20);
+check(world.health(1) === 20 && world.len() === 2, "the world lost an entity");
+world.release();
+
+const boxed = Native.createPtr(3n);
+Native.changeItem(boxed, 9n);
+boxed.release();
+
+const logger = Native.createLogger();
+check(!logger.flush() && logger.flush(), "the logger did not count its flushes");
+logger.release();
+"#;
+
+#[test]
+fn js_bindings() {
+    let bag = create_js_bag();
+    let js = JsRenderer.render(&bag).unwrap();
+
+    // structs are read and written at their wasm32 offsets
+    assert!(js.contains("    static $size = 16;\n    static $align = 8;\n"));
+    assert!(js.contains("x1: types.u64.$read(view, ptr + 8),"));
+    assert!(js.contains("SubModule_TestStruct2.$write(view, ptr + 0, value.nested);"));
+    assert!(js.contains("        TestStruct2: SubModule_TestStruct2,"));
+
+    // the glue runs headless in node against the wasm32 build when both are available
+    let rustc = env::var("RUSTC").unwrap_or("rustc".to_string());
+    let libdir = Command::new(rustc)
+        .args([
+            "--print",
+            "target-libdir",
+            "--target",
+            "wasm32-unknown-unknown",
+        ])
+        .output()
+        .unwrap();
+    let libdir = PathBuf::from(String::from_utf8(libdir.stdout).unwrap().trim());
+    if !libdir.exists() {
+        eprintln!(
+            "skipping the javascript run, the wasm32-unknown-unknown target is not installed"
+        );
+        return;
+    }
+    if Command::new("node").arg("--version").output().is_err() {
+        eprintln!("skipping the javascript run, node was not found");
+        return;
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("js_bindings");
+    let status = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args([
+            "build",
+            "--lib",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--target-dir",
+        ])
+        .arg(dir.join("target"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let wasm = dir.join("target/wasm32-unknown-unknown/debug/all_features.wasm");
+
+    // the glue is rendered from the bag of the wasm32 build, which has its own layouts
+    fs::write(dir.join("dump.mjs"), JS_DUMP).unwrap();
+    let dump = Command::new("node")
+        .arg(dir.join("dump.mjs"))
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    assert!(
+        dump.status.success(),
+        "{}",
+        String::from_utf8_lossy(&dump.stderr)
+    );
+    let wasm_bag = PolyBag::from_json(&String::from_utf8(dump.stdout).unwrap()).unwrap();
+    let registry = wasm_bag.root_module().reachable_structs(|_| true);
+    let registry = registry.iter().find(|s| s.name == "Registry").unwrap();
+    assert_eq!(registry.size, 8);
+
+    fs::write(
+        dir.join("AllFeatures.mjs"),
+        JsRenderer.render(&wasm_bag).unwrap(),
+    )
+    .unwrap();
+    fs::write(dir.join("harness.mjs"), JS_HARNESS).unwrap();
+    let output = Command::new("node")
+        .arg(dir.join("harness.mjs"))
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
 #[test]
 fn bind() {
     // remove all current rendered templates
@@ -17,25 +1390,45 @@ fn bind() {
     }
 
     // create the PolyBag
-    let bag = PolyBag::new("Native")
-        .register_impl::<TestStruct>()
-        .register_function::<pointer_test>()
-        .register_function::<execute>()
-        .register_function::<get_u32>()
-        .register_function::<create_opaque>()
-        .register_function::<create_ptr>()
-        .register_function::<change_item>()
-        .register_function::<sub_module::sub_module_function>();
+    let bag = create_bag();
 
-    // render the csharp data to a file
+    // write the json ir to a file
     fs::create_dir_all(OUTPUT_DIR).unwrap();
+    fs::write(
+        PathBuf::from(OUTPUT_DIR).join("AllFeatures.json"),
+        bag.to_json().unwrap(),
+    )
+    .unwrap();
+
+    // render the csharp data to a file
     fs::write(
         PathBuf::from(OUTPUT_DIR).join("AllFeatures.cs"),
         CSharpRenderer {
             lib_name: "all_features".to_string(),
             namespace: "AllFeatures".to_string(),
+            import_style: ImportStyle::LibraryImport,
         }
-        .render(&bag),
+        .render(&bag)
+        .unwrap(),
+    )
+    .unwrap();
+
+    // render the php data to a file
+    fs::write(
+        PathBuf::from(OUTPUT_DIR).join("AllFeatures.php"),
+        PhpRenderer {
+            lib_name: "all_features".to_string(),
+            namespace: "AllFeatures".to_string(),
+        }
+        .render(&bag)
+        .unwrap(),
+    )
+    .unwrap();
+
+    // render the javascript data to a file
+    fs::write(
+        PathBuf::from(OUTPUT_DIR).join("AllFeatures.mjs"),
+        JsRenderer.render(&create_js_bag()).unwrap(),
     )
     .unwrap();
 }
@@ -1,5 +1,8 @@
 use polygen::{items::types::PolyBox, polygen};
 
+// embed the bag so bindings can be generated from the built library
+polygen::export_bag!("Native");
+
 #[polygen]
 pub struct MyStruct {
     item: u32,
@@ -1,8 +1,12 @@
 use std::{fs, path::PathBuf};
 
 use polygen::PolyBag;
-use polygen_csharp::CSharpRenderer;
-use simple_lib::{create_boxed, set_item, MyStruct};
+use polygen_csharp::{CSharpRenderer, ImportStyle};
+use polygen_js::JsRenderer;
+use polygen_php::PhpRenderer;
+
+// make sure the library is linked so its items end up in the registry
+use simple_lib::__polygen_bag_json;
 
 static OUTPUT_DIR: &str = "target/polygen";
 
@@ -15,10 +19,7 @@ fn bind() {
     }
 
     // create the PolyBag
-    let bag = PolyBag::new("Native")
-        .register_impl::<MyStruct>()
-        .register_function::<create_boxed>()
-        .register_function::<set_item>();
+    let bag = PolyBag::new("Native").register_all();
 
     // render the csharp data to a file
     fs::create_dir_all(OUTPUT_DIR).unwrap();
@@ -27,8 +28,41 @@ fn bind() {
         CSharpRenderer {
             lib_name: "simple_lib".to_string(),
             namespace: "SimpleLib".to_string(),
+            import_style: ImportStyle::DllImport,
+        }
+        .render(&bag)
+        .unwrap(),
+    )
+    .unwrap();
+
+    // render the php data to a file
+    fs::write(
+        PathBuf::from(OUTPUT_DIR).join("SimpleLib.php"),
+        PhpRenderer {
+            lib_name: "simple_lib".to_string(),
+            namespace: "SimpleLib".to_string(),
         }
-        .render(&bag),
+        .render(&bag)
+        .unwrap(),
     )
     .unwrap();
+
+    // render the javascript data to a file
+    fs::write(
+        PathBuf::from(OUTPUT_DIR).join("SimpleLib.mjs"),
+        JsRenderer.render(&bag).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn embedded_bag() {
+    let mut len = 0;
+    let json = unsafe {
+        let ptr = __polygen_bag_json(&mut len);
+        std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).unwrap()
+    };
+
+    let bag = PolyBag::new("Native").register_all();
+    assert_eq!(json, bag.to_json().unwrap());
 }
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 
 use heck::ToPascalCase;
 use once_cell::sync::Lazy;
-use polygen::items::{PolyStruct, PolyType};
+use polygen::ir::{PolyFnPtr, PolyStruct, PolyType, PolyValue, StructKind};
 
 static PRIMITIVES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     HashMap::from([
@@ -21,23 +21,31 @@ static PRIMITIVES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
         ("bool", "bool"),
         ("f32", "float"),
         ("f64", "double"),
+        ("c_void", "void"),
     ])
 });
 
 pub fn render_typename(t: Option<&PolyType>) -> String {
     match t {
-        None => format!("void"),
+        None => "void".to_string(),
         Some(PolyType::Pointer(t)) => format!("{}*", render_typename_data(Some(t))),
-        Some(PolyType::Primitive(p)) => format!("{}", PRIMITIVES.get(p).unwrap()),
+        Some(PolyType::Primitive(p)) => render_primitive(p),
         Some(PolyType::Struct(s)) => render_structname(s),
+        Some(PolyType::FnPtr(f)) => render_fn_pointer(f),
     }
 }
 
 pub fn render_typename_data(t: Option<&PolyType>) -> String {
     match t {
-        None => format!("void"),
+        None => "void".to_string(),
         Some(PolyType::Pointer(t)) => format!("{}*", render_typename_data(Some(t))),
-        Some(PolyType::Primitive(p)) => format!("{}", PRIMITIVES.get(p).unwrap()),
+        Some(PolyType::Primitive(p)) => render_primitive(p),
+        Some(PolyType::FnPtr(f)) => render_fn_pointer(f),
+        // handles and callbacks share a single non generic data struct
+        Some(PolyType::Struct(s)) if is_handle(s) || is_callback(s) => {
+            let structname = render_structname_base(s);
+            format!("{structname}.Data")
+        }
         Some(PolyType::Struct(s)) => {
             let structname = render_structname(s);
             format!("{structname}.Data")
@@ -45,17 +53,109 @@ pub fn render_typename_data(t: Option<&PolyType>) -> String {
     }
 }
 
+fn render_primitive(p: &str) -> String {
+    let primitive = PRIMITIVES.get(p);
+    primitive
+        .expect("unsupported primitives are rejected by render")
+        .to_string()
+}
+
+/// Returns true if the struct is a boxed value that is rendered as a `SafeHandle`.
+pub fn is_handle(s: &PolyStruct) -> bool {
+    matches!(s.kind, StructKind::Handle(_))
+}
+
+/// Returns true if the type is a struct that is rendered as a `SafeHandle`.
+pub fn is_handle_type(t: &PolyType) -> bool {
+    matches!(t, PolyType::Struct(s) if is_handle(s))
+}
+
+/// Returns true if the struct is a foreign function that is created from a delegate.
+pub fn is_callback(s: &PolyStruct) -> bool {
+    s.module == "::polygen" && s.name == "PolyCallback"
+}
+
+/// Renders an unmanaged function pointer with the native types of its signature.
+pub fn render_fn_pointer(f: &PolyFnPtr) -> String {
+    let inputs = f.inputs.iter().map(|t| render_typename_data(Some(t)));
+    let output = render_typename_data(f.output.as_deref());
+    let types = inputs.chain([output]).collect::<Vec<_>>().join(", ");
+    format!("delegate* unmanaged[Cdecl]<{types}>")
+}
+
+/// Renders the name of the delegate type generated for a function pointer signature.
+pub fn render_delegate_name(f: &PolyFnPtr) -> String {
+    let inputs = utils::join(f.inputs.iter(), "", render_mangled);
+    match &f.output {
+        None => format!("Fn{inputs}"),
+        Some(output) => format!("Fn{inputs}Returns{}", render_mangled(output)),
+    }
+}
+
+fn render_mangled(t: &PolyType) -> String {
+    match t {
+        PolyType::Primitive(p) => p.to_pascal_case(),
+        PolyType::Pointer(t) => format!("{}Ptr", render_mangled(t)),
+        PolyType::Struct(s) => {
+            let generics = utils::join(s.generics.iter(), "", |g| render_mangled(&g.ty));
+            format!("{}{generics}", render_structname_base(s).replace('.', ""))
+        }
+        PolyType::FnPtr(f) => render_delegate_name(f),
+    }
+}
+
 pub fn render_structname(s: &PolyStruct) -> String {
+    // function pointers can not be generic arguments, so their delegate is used instead
+    let mut generics = utils::join(s.generics.iter(), ", ", |g| match &g.ty {
+        PolyType::FnPtr(f) => render_delegate_name(f),
+        t => render_typename(Some(t)),
+    });
+    if !generics.is_empty() {
+        generics = format!("<{generics}>");
+    }
+
+    let name = render_structname_base(s);
+    format!("{name}{generics}")
+}
+
+/// Renders the name of a struct without its generic arguments.
+pub fn render_structname_base(s: &PolyStruct) -> String {
     let mut modules = utils::join(s.module.split("::").skip(1), ".", |m| m.to_pascal_case());
-    if modules.len() > 0 {
+    if !modules.is_empty() {
         modules = format!("{modules}.");
     }
 
-    let mut generics = utils::join(s.generics.iter(), ", ", |g| render_typename(Some(g.ty)));
-    if generics.len() > 0 {
-        generics = format!("<{generics}>");
+    let name = s.name.to_pascal_case();
+    format!("{modules}{name}")
+}
+
+/// Renders the name of the interface that the objects of a trait implement.
+pub fn render_interface_name(s: &PolyStruct) -> String {
+    let name = render_structname_base(s);
+    match name.rsplit_once('.') {
+        Some((modules, name)) => format!("{modules}.I{name}"),
+        None => format!("I{name}"),
     }
+}
 
-    let name = s.name.to_pascal_case();
-    format!("{modules}{name}{generics}")
+/// Renders a constant value as a literal of its type.
+pub fn render_value(ty: &PolyType, value: &PolyValue) -> String {
+    let float = matches!(ty, PolyType::Primitive(p) if p == "f32");
+    let (typename, suffix) = match float {
+        true => ("float", "f"),
+        false => ("double", ""),
+    };
+
+    match value {
+        PolyValue::Bool(v) => v.to_string(),
+        PolyValue::Int(v) => v.to_string(),
+        PolyValue::UInt(v) => v.to_string(),
+        PolyValue::Float(v) if v.is_nan() => format!("{typename}.NaN"),
+        PolyValue::Float(v) if v.is_infinite() && *v > 0.0 => {
+            format!("{typename}.PositiveInfinity")
+        }
+        PolyValue::Float(v) if v.is_infinite() => format!("{typename}.NegativeInfinity"),
+        PolyValue::Float(v) if float => format!("{:?}{suffix}", *v as f32),
+        PolyValue::Float(v) => format!("{v:?}"),
+    }
 }
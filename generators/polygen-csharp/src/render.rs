@@ -1,51 +1,156 @@
 use crate::{
-    polytype::{render_typename, render_typename_data},
+    polytype::{
+        is_callback, is_handle, is_handle_type, render_delegate_name, render_interface_name,
+        render_structname_base, render_typename, render_typename_data, render_value,
+    },
     utils,
 };
 
+use std::collections::HashSet;
+
 use heck::{ToLowerCamelCase, ToPascalCase};
 use indent::indent_by;
 use indoc::formatdoc;
 use polygen::{
-    items::{FieldType, FnInput, ImplFn, PolyFn, PolyImpl, PolyStruct, PolyType, StructField},
+    ir::{
+        FieldType, FnInput, FnParams, ImplFn, PolyConst, PolyFn, PolyFnPtr, PolyImpl, PolyStruct,
+        PolyType, StructField, StructKind, TraitFn,
+    },
+    validate::ProblemKind,
     PolyBag, PolyMod,
 };
+use thiserror::Error;
+
+/// The kind of declaration used to import native functions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStyle {
+    /// Classic `[DllImport]` declarations that are marshalled at runtime.
+    #[default]
+    DllImport,
+    /// `[LibraryImport]` declarations that are generated at compile time.
+    ///
+    /// Requires .NET 7 or newer and disables runtime marshalling for the assembly.
+    LibraryImport,
+}
+
+#[derive(Debug, Error)]
+pub enum CSharpError {
+    #[error("The type `{0}` has no C# equivalent")]
+    UnsupportedType(String),
+}
 
 pub struct CSharpRenderer {
     pub lib_name: String,
     pub namespace: String,
+    pub import_style: ImportStyle,
 }
 
 impl CSharpRenderer {
-    pub fn render(&self, bag: &PolyBag) -> String {
+    pub fn render(&self, bag: &PolyBag) -> Result<String, CSharpError> {
+        // primitives are looked up in a fixed table, which only has the supported ones
+        let unsupported = bag.validate().into_iter().find_map(|p| match p.kind {
+            ProblemKind::UnsupportedType(t) => Some(t),
+            _ => None,
+        });
+        if let Some(t) = unsupported {
+            return Err(CSharpError::UnsupportedType(t));
+        }
+
         let namespace = &self.namespace;
-        let module = indent_by(4, self.render_module(bag.root_module()));
+        let module = indent_by(4, self.render_root(bag));
+        let header = match self.import_style {
+            ImportStyle::DllImport => formatdoc! {"
+                using System;
+                using System.Runtime.InteropServices;
+                "
+            },
+            ImportStyle::LibraryImport => formatdoc! {"
+                using System;
+                using System.Runtime.CompilerServices;
+                using System.Runtime.InteropServices;
 
-        formatdoc! {"
-            using System;
-            using System.Runtime.InteropServices;
-            
+                [assembly: DisableRuntimeMarshalling]
+                "
+            },
+        };
+
+        Ok(formatdoc! {"
+            {header}
             namespace {namespace}
             {{
                 {module}
             }}
             "
+        })
+    }
+
+    fn partial_keyword(&self) -> &'static str {
+        match self.import_style {
+            ImportStyle::DllImport => "",
+            ImportStyle::LibraryImport => " partial",
+        }
+    }
+
+    fn render_import(
+        &self,
+        export_name: &str,
+        out_data: &str,
+        export_params: &str,
+        trivial: bool,
+    ) -> String {
+        let lib_name = &self.lib_name;
+        let mut output = match self.import_style {
+            ImportStyle::DllImport => formatdoc! {"
+                [DllImport(\"{lib_name}\", CallingConvention = CallingConvention.Cdecl)]"
+            },
+            ImportStyle::LibraryImport => formatdoc! {"
+                [LibraryImport(\"{lib_name}\")]
+                [UnmanagedCallConv(CallConvs = new[] {{ typeof(CallConvCdecl) }})]"
+            },
+        };
+
+        if trivial {
+            output += "\n[SuppressGCTransition]";
         }
+
+        let declaration = match self.import_style {
+            ImportStyle::DllImport => "static unsafe extern",
+            ImportStyle::LibraryImport => "static unsafe partial",
+        };
+
+        output += &format!("\nprivate {declaration} {out_data} {export_name}({export_params});");
+        output
     }
 
-    fn render_struct(&self, s: &PolyStruct, i: Option<&PolyImpl>) -> String {
+    fn render_struct(&self, root: &str, s: &PolyStruct, i: Option<&PolyImpl>) -> String {
         let name = s.name.to_pascal_case();
+        let partial = self.partial_keyword();
+        let docs = render_docs(&s.docs, &[]);
 
+        let layout = render_layout(s);
         let fields = indent_by(
             8,
-            utils::join(s.fields.iter(), "\n", |f| self.render_struct_field(f)),
+            utils::join(s.fields.iter(), "\n", |f| self.render_struct_field(s, f)),
         );
 
+        let mut constants = utils::join(i.iter().flat_map(|i| &i.constants), "\n", render_constant);
+        if !constants.is_empty() {
+            constants = indent_by(4, format!("\n\n{constants}"));
+        }
+
+        let mut properties = utils::join(s.fields.iter().filter(|f| f.visible), "\n", |f| {
+            self.render_struct_property(f)
+        });
+        if !properties.is_empty() {
+            properties = indent_by(4, format!("\n\n{properties}"));
+        }
+
         let functions = match i {
-            None => format!(""),
+            None => String::new(),
+            Some(i) if i.functions.is_empty() => String::new(),
             Some(i) => {
-                let mut functions = format!("");
-                for f in i.functions {
+                let mut functions = format!("\n\nstatic {name}() => {root}.VerifyAbi();");
+                for f in &i.functions {
                     functions += "\n\n";
                     functions += &self.render_struct_function(f);
                 }
@@ -54,17 +159,17 @@ impl CSharpRenderer {
         };
 
         formatdoc! {"
-            public class {name}
+            {docs}public{partial} class {name}
             {{
                 internal Data _data;
-                public Data data => _data;
+                public Data data => _data;{constants}
 
                 internal {name}(Data newData)
                 {{
                     _data = newData;
-                }}
+                }}{properties}
 
-                [StructLayout(LayoutKind.Sequential)]
+                {layout}
                 public struct Data
                 {{
                     {fields}
@@ -74,68 +179,164 @@ impl CSharpRenderer {
     }
 
     fn render_function(&self, f: &PolyFn) -> String {
-        let lib_name = &self.lib_name;
-        let export_name = f.export_name;
+        let export_name = &f.export_name;
         let name = f.name.to_pascal_case();
         let out_type = render_typename(f.params.output.as_ref());
         let out_data = render_typename_data(f.params.output.as_ref());
+        let unsafe_keyword = unsafe_keyword(&f.params);
 
         let export_params = utils::join(f.params.inputs.iter(), ", ", |i| {
             let name = i.name.to_lower_camel_case();
-            let ty = render_typename_data(Some(i.ty));
+            let ty = render_typename_data(Some(&i.ty));
             format!("{ty} {name}")
         });
 
         let func_params = utils::join(f.params.inputs.iter(), ", ", |i| {
             let name = i.name.to_lower_camel_case();
-            let ty = render_typename(Some(i.ty));
+            let ty = render_typename(Some(&i.ty));
             format!("{ty} {name}")
         });
 
-        let convert_params = utils::join(f.params.inputs.iter(), ", ", |i| match i.ty {
+        let convert_params = utils::join(f.params.inputs.iter(), ", ", |i| match &i.ty {
             PolyType::Struct(_) => format!("{}._data", i.name.to_lower_camel_case()),
             _ => i.name.to_lower_camel_case(),
         });
 
-        let convert_call = match f.params.output {
+        let convert_call = match &f.params.output {
             Some(PolyType::Struct(_)) => {
                 format!("new {out_type}({export_name}({convert_params}))")
             }
             _ => format!("{export_name}({convert_params})"),
         };
 
+        let import = self.render_import(export_name, &out_data, &export_params, f.trivial);
+        let body = render_body(&f.params, &convert_call, false);
+        let docs = render_docs(&f.docs, &f.params.inputs);
         formatdoc! {"
-            [DllImport(\"{lib_name}\", CallingConvention = CallingConvention.Cdecl)]
-            private static unsafe extern {out_data} {export_name}({export_params});
-            public static {out_type} {name}({func_params}) => unsafe {{ {convert_call} }};"
+            {import}
+            {docs}public static{unsafe_keyword} {out_type} {name}({func_params}){body}"
         }
     }
 
-    fn render_module(&self, m: &PolyMod) -> String {
+    fn render_root(&self, bag: &PolyBag) -> String {
+        let m = bag.root_module();
         let name = m.name().to_pascal_case();
-        let items = indent_by(4, self.render_module_items(m));
+        let partial = self.partial_keyword();
+        let mut items = self.render_module_items(&name, m);
+
+        let mut delegates = Vec::new();
+        collect_delegates(m, &mut delegates);
+        if !delegates.is_empty() {
+            let delegates = utils::join(delegates.iter(), "\n\n", |(name, f)| {
+                render_delegate(name, f)
+            });
+            items = format!("{delegates}\n\n{items}");
+        }
+        let items = indent_by(4, items);
+
+        let fingerprints = m.fingerprints();
+        let fingerprints = indent_by(
+            12,
+            utils::join(fingerprints.iter(), "\n", |(export_name, fingerprint)| {
+                format!("(\"{export_name}\", {fingerprint}UL),")
+            }),
+        );
 
+        let import = indent_by(
+            4,
+            self.render_import(
+                "__polygen_abi_fingerprint",
+                "ulong",
+                "byte* name, nuint len",
+                false,
+            ),
+        );
+
+        // the static constructor verifies the abi before any native function is called
+        // every other class with native functions triggers it through VerifyAbi
         formatdoc! {"
-            public static class {name}
+            public static{partial} class {name}
+            {{
+                static {name}()
+                {{
+                    (string, ulong)[] fingerprints =
+                    {{
+                        {fingerprints}
+                    }};
+
+                    foreach (var (export, fingerprint) in fingerprints)
+                    {{
+                        if (Fingerprint(export) != fingerprint)
+                        {{
+                            throw new InvalidOperationException($\"The bindings are out of date, `{{export}}` does not match the native library.\");
+                        }}
+                    }}
+                }}
+
+                internal static void VerifyAbi() {{ }}
+
+                private static unsafe ulong Fingerprint(string export)
+                {{
+                    var bytes = System.Text.Encoding.UTF8.GetBytes(export);
+                    fixed (byte* ptr = bytes)
+                    {{
+                        return __polygen_abi_fingerprint(ptr, (nuint)bytes.Length);
+                    }}
+                }}
+
+                {import}
+
+                {items}
+            }}"
+        }
+    }
+
+    fn render_module(&self, root: &str, m: &PolyMod) -> String {
+        let name = m.name().to_pascal_case();
+        let partial = self.partial_keyword();
+        let mut items = self.render_module_items(root, m);
+        if m.functions().next().is_some() {
+            items = format!("static {name}() => {root}.VerifyAbi();\n\n{items}");
+        }
+        let items = indent_by(4, items);
+
+        formatdoc! {"
+            public static{partial} class {name}
             {{
                 {items}
             }}"
         }
     }
 
-    fn render_module_items(&self, m: &PolyMod) -> String {
-        let mut output = String::new();
-        let structs = utils::join(m.structs(), "\n\n", |(s, i)| self.render_struct(s, i));
+    fn render_module_items(&self, root: &str, m: &PolyMod) -> String {
+        let mut output = utils::join(m.constants(), "\n", render_constant);
+
+        // every instance of a generic struct is rendered as the same generic class
+        let mut names = HashSet::new();
+        let structs = m.structs().filter(|(s, _)| names.insert(&s.name));
+        let structs = utils::join(structs, "\n\n", |(s, i)| match s {
+            s if is_handle(s) => self.render_handle(root, s, i),
+            s if is_callback(s) => self.render_callback(s),
+            PolyStruct {
+                kind: StructKind::VTable(methods),
+                ..
+            } => self.render_vtable(s, methods),
+            s => self.render_struct(root, s, i),
+        });
         let functions = utils::join(m.functions(), "\n\n", |f| self.render_function(f));
-        let modules = utils::join(m.modules(), "\n\n", |m| self.render_module(m));
+        let modules = utils::join(m.modules(), "\n\n", |m| self.render_module(root, m));
+
+        if !output.is_empty() && !structs.is_empty() {
+            output += "\n\n";
+        }
 
         output += &structs;
-        if output.len() > 0 && functions.len() > 0 {
+        if !output.is_empty() && !functions.is_empty() {
             output += "\n\n";
         }
 
         output += &functions;
-        if output.len() > 0 && modules.len() > 0 {
+        if !output.is_empty() && !modules.is_empty() {
             output += "\n\n";
         }
 
@@ -143,23 +344,68 @@ impl CSharpRenderer {
         output
     }
 
-    fn render_struct_field(&self, f: &StructField) -> String {
+    fn render_struct_field(&self, s: &PolyStruct, f: &StructField) -> String {
         let name = f.name.to_lower_camel_case();
-        let ty = match f.ty {
+        let ty = match &f.ty {
             FieldType::Generic(g) => g.to_string(),
-            FieldType::Typed(t) => render_typename(Some(t)),
+            FieldType::Typed(t) => render_typename_data(Some(t)),
         };
-        let vis = match f.visible {
-            false => "internal",
-            true => "public",
+        let offset = match has_explicit_layout(s) {
+            true => format!("[FieldOffset({})] ", f.offset),
+            false => String::new(),
         };
+        // fields are only publicly exposed through the properties on the wrapper class
+        format!("{offset}internal {ty} {name};")
+    }
+
+    fn render_struct_property(&self, f: &StructField) -> String {
+        let name = f.name.to_pascal_case();
+        let field = f.name.to_lower_camel_case();
+        let (ty, get, set) = match &f.ty {
+            FieldType::Generic(g) => (
+                g.to_string(),
+                format!("_data.{field}"),
+                format!("_data.{field} = value"),
+            ),
+            // handles read from a field do not own the value
+            FieldType::Typed(t @ PolyType::Struct(s)) if is_handle(s) => {
+                let ty = render_typename(Some(t));
+                (
+                    ty.clone(),
+                    format!("new {ty}(_data.{field}, false)"),
+                    format!("_data.{field} = value._data"),
+                )
+            }
+            FieldType::Typed(t @ PolyType::Struct(_)) => {
+                let ty = render_typename(Some(t));
+                (
+                    ty.clone(),
+                    format!("new {ty}(_data.{field})"),
+                    format!("_data.{field} = value._data"),
+                )
+            }
+            FieldType::Typed(t) => (
+                render_typename(Some(t)),
+                format!("_data.{field}"),
+                format!("_data.{field} = value"),
+            ),
+        };
+        let unsafe_keyword = match &f.ty {
+            FieldType::Typed(PolyType::Pointer(_) | PolyType::FnPtr(_)) => " unsafe",
+            _ => "",
+        };
+        let docs = render_docs(&f.docs, &[]);
 
-        format!("{vis} {ty} {name};")
+        match f.readonly {
+            true => format!("{docs}public{unsafe_keyword} {ty} {name} => {get};"),
+            false => format!(
+                "{docs}public{unsafe_keyword} {ty} {name} {{ get => {get}; set => {set}; }}"
+            ),
+        }
     }
 
     fn render_struct_function(&self, f: &ImplFn) -> String {
-        let lib_name = &self.lib_name;
-        let export_name = f.export_name;
+        let export_name = &f.export_name;
         let name = f.name.to_pascal_case();
         let out_type = render_typename(f.params.output.as_ref());
         let out_data = render_typename_data(f.params.output.as_ref());
@@ -168,10 +414,11 @@ impl CSharpRenderer {
             false => " static",
             true => "",
         };
+        let unsafe_keyword = unsafe_keyword(&f.params);
 
         let export_params = utils::join(f.params.inputs.iter(), ", ", |i| {
             let name = i.name.to_lower_camel_case();
-            let ty = render_typename_data(Some(i.ty));
+            let ty = render_typename_data(Some(&i.ty));
             format!("{ty} {name}")
         });
 
@@ -180,49 +427,496 @@ impl CSharpRenderer {
             ", ",
             |i| {
                 let name = i.name.to_lower_camel_case();
-                let ty = render_typename(Some(i.ty));
+                let ty = render_typename(Some(&i.ty));
                 format!("{ty} {name}")
             },
         );
 
-        let convert_params = utils::join(f.params.inputs.iter(), ", ", |i| match i.ty {
+        let convert_params = utils::join(f.params.inputs.iter(), ", ", |i| match &i.ty {
             PolyType::Pointer(_) if i.name == "self" => format!("__polygen_self_ptr"),
             PolyType::Struct(_) if i.name == "self" => format!("this._data"),
+            PolyType::Struct(_) => format!("{}._data", i.name.to_lower_camel_case()),
             _ => i.name.to_lower_camel_case(),
         });
 
-        let convert_call = match f.params.output {
+        let convert_call = match &f.params.output {
             Some(PolyType::Struct(_)) => {
                 format!("new {out_type}({export_name}({convert_params}))")
             }
             _ => format!("{export_name}({convert_params})"),
         };
 
-        let conversion = match self_input {
-            Some(FnInput {
-                name: _,
-                ty: PolyType::Pointer(_),
-            }) => {
+        let fixed_self = matches!(self_input.map(|i| &i.ty), Some(PolyType::Pointer(_)));
+        let import = self.render_import(export_name, &out_data, &export_params, f.trivial);
+        let body = render_body(&f.params, &convert_call, fixed_self);
+        let docs = render_docs(&f.docs, &f.params.inputs);
+        formatdoc! {"
+            {import}
+            {docs}public{static_keyword}{unsafe_keyword} {out_type} {name}({func_params}){body}"
+        }
+    }
+
+    /// Renders a callback that keeps its delegate alive until the native library drops it.
+    fn render_callback(&self, s: &PolyStruct) -> String {
+        let name = s.name.to_pascal_case();
+        let holder = render_structname_base(s);
+        let docs = render_docs(&s.docs, &[]);
+        let fields = indent_by(
+            4,
+            utils::join(s.fields.iter(), "\n", |f| {
+                format!("internal IntPtr {};", f.name.to_lower_camel_case())
+            }),
+        );
+
+        formatdoc! {"
+            public static class {name}
+            {{
+                [StructLayout(LayoutKind.Sequential)]
+                public struct Data
+                {{
+                    {fields}
+                }}
+
+                [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+                private delegate void ReleaseFn(IntPtr userData);
+
+                // stored in a static field so the release delegate is never collected
+                private static readonly ReleaseFn release = userData => GCHandle.FromIntPtr(userData).Free();
+                internal static readonly IntPtr ReleasePtr = Marshal.GetFunctionPointerForDelegate(release);
+            }}
+
+            {docs}public sealed class {name}<T> where T : Delegate
+            {{
+                internal {holder}.Data _data;
+                public {holder}.Data data => _data;
+
+                /// <summary>
+                /// Wraps a delegate, which is kept alive until the native library drops the callback.
+                /// </summary>
+                public {name}(T callback)
+                {{
+                    var handle = GCHandle.Alloc(callback);
+                    _data = new {holder}.Data
+                    {{
+                        func = Marshal.GetFunctionPointerForDelegate(callback),
+                        userData = GCHandle.ToIntPtr(handle),
+                        drop = {holder}.ReleasePtr,
+                    }};
+                }}
+
+                internal {name}({holder}.Data newData)
+                {{
+                    _data = newData;
+                }}
+            }}"
+        }
+    }
+
+    /// Renders the interface of a trait and an adapter that fills its vtable from an object.
+    ///
+    /// The object is kept alive by a `GCHandle` until the native library drops the vtable.
+    fn render_vtable(&self, s: &PolyStruct, methods: &[TraitFn]) -> String {
+        let name = s.name.to_pascal_case();
+        let interface = format!("I{name}");
+        let docs = render_docs(&s.docs, &[]);
+
+        let interface_methods = indent_by(
+            4,
+            utils::join(methods.iter(), "\n", |f| {
+                let docs = render_docs(&f.docs, &f.params.inputs);
+                let out_type = render_typename(f.params.output.as_ref());
+                let unsafe_keyword = unsafe_keyword(&f.params).trim_start();
+                let params = utils::join(f.params.inputs.iter(), ", ", |i| {
+                    let ty = render_typename(Some(&i.ty));
+                    format!("{ty} {}", i.name.to_lower_camel_case())
+                });
+                match unsafe_keyword.is_empty() {
+                    true => format!("{docs}{out_type} {}({params});", f.name.to_pascal_case()),
+                    false => format!(
+                        "{docs}{unsafe_keyword} {out_type} {}({params});",
+                        f.name.to_pascal_case()
+                    ),
+                }
+            }),
+        );
+
+        // every native function resolves the object from the user data and calls it
+        let mut trampolines = Vec::new();
+        for f in methods {
+            let method = f.name.to_pascal_case();
+            let field = f.name.to_lower_camel_case();
+            let out_data = render_typename_data(f.params.output.as_ref());
+            let params = utils::join(f.params.inputs.iter(), "", |i| {
+                let ty = render_typename_data(Some(&i.ty));
+                format!(", {ty} {}", i.name.to_lower_camel_case())
+            });
+            let args = utils::join(f.params.inputs.iter(), ", ", |i| match &i.ty {
+                t @ PolyType::Struct(_) => {
+                    let ty = render_typename(Some(t));
+                    format!("new {ty}({})", i.name.to_lower_camel_case())
+                }
+                _ => i.name.to_lower_camel_case(),
+            });
+            let lambda_args = utils::join(f.params.inputs.iter(), "", |i| {
+                format!(", {}", i.name.to_lower_camel_case())
+            });
+            let call = match &f.params.output {
+                Some(PolyType::Struct(_)) => format!("Target(userData).{method}({args})._data"),
+                _ => format!("Target(userData).{method}({args})"),
+            };
+
+            trampolines.push(formatdoc! {"
+                [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+                private delegate {out_data} {method}Fn(IntPtr userData{params});
+                private static readonly {method}Fn {field} = (userData{lambda_args}) => {call};
+                private static readonly IntPtr {field}Ptr = Marshal.GetFunctionPointerForDelegate({field});"
+            });
+        }
+
+        trampolines.push(formatdoc! {"
+            [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+            private delegate void DropFn(IntPtr userData);
+            private static readonly DropFn drop = userData => GCHandle.FromIntPtr(userData).Free();
+            private static readonly IntPtr dropPtr = Marshal.GetFunctionPointerForDelegate(drop);"
+        });
+        let trampolines = indent_by(4, trampolines.join("\n\n"));
+
+        let assign = indent_by(
+            12,
+            utils::join(methods.iter(), "\n", |f| {
+                let field = f.name.to_lower_camel_case();
+                format!("{field} = {field}Ptr,")
+            }),
+        );
+
+        let layout = render_layout(s);
+        let fields = indent_by(
+            8,
+            utils::join(s.fields.iter(), "\n", |f| {
+                let name = f.name.to_lower_camel_case();
+                format!("[FieldOffset({})] internal IntPtr {name};", f.offset)
+            }),
+        );
+
+        formatdoc! {"
+            {docs}public interface {interface}
+            {{
+                {interface_methods}
+            }}
+
+            public sealed unsafe class {name}
+            {{
+                internal Data _data;
+                public Data data => _data;
+
+                /// <summary>
+                /// Implements the native trait with an object, which is kept alive until the native library drops it.
+                /// </summary>
+                public {name}({interface} target)
+                {{
+                    _data = new Data
+                    {{
+                        userData = GCHandle.ToIntPtr(GCHandle.Alloc(target)),
+                        {assign}
+                        drop = dropPtr,
+                    }};
+                }}
+
+                internal {name}(Data newData)
+                {{
+                    _data = newData;
+                }}
+
+                private static {interface} Target(IntPtr userData) => ({interface})GCHandle.FromIntPtr(userData).Target!;
+
+                {trampolines}
+
+                {layout}
+                public struct Data
+                {{
+                    {fields}
+                }}
+            }}"
+        }
+    }
+
+    fn render_handle(&self, root: &str, s: &PolyStruct, i: Option<&PolyImpl>) -> String {
+        let name = s.name.to_pascal_case();
+        let partial = self.partial_keyword();
+        let holder = render_structname_base(s);
+        let data = render_typename_data(Some(&PolyType::Struct(s.clone())));
+        let docs = render_docs(&s.docs, &[]);
+
+        let layout = render_layout(s);
+        let fields = indent_by(
+            4,
+            utils::join(s.fields.iter(), "\n", |f| self.render_struct_field(s, f)),
+        );
+
+        let import = self.render_import(
+            "__polygen_drop",
+            "void",
+            "nuint ptr, delegate* unmanaged[Cdecl]<void*, void> drop",
+            false,
+        );
+
+        // trait objects implement the interface of their trait through their methods
+        let interface = match &s.kind {
+            StructKind::Handle(Some(t)) => match t.as_ref() {
+                PolyType::Struct(t) => format!(", {}", render_interface_name(t)),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        let functions = match i {
+            None => String::new(),
+            Some(i) if i.functions.is_empty() => String::new(),
+            Some(i) => {
+                let mut functions = format!("\n\nstatic {name}() => {root}.VerifyAbi();");
+                for f in &i.functions {
+                    functions += "\n\n";
+                    functions += &self.render_struct_function(f);
+                }
+                functions
+            }
+        };
+
+        let handle_members = formatdoc! {"
+            internal {data} _data;
+
+            internal {name}({data} newData, bool ownsHandle = true) : base(IntPtr.Zero, ownsHandle)
+            {{
+                _data = newData;
+                SetHandle((IntPtr)newData.ptr);
+            }}
+
+            public override bool IsInvalid => handle == IntPtr.Zero;
+
+            protected override bool ReleaseHandle()
+            {{
+                {holder}.Release(_data);
+                return true;
+            }}{functions}"
+        };
+
+        let holder_members = formatdoc! {"
+            {layout}
+            public unsafe struct Data
+            {{
+                {fields}
+            }}
+
+            internal static unsafe void Release(Data data) => __polygen_drop(data.ptr, data.drop);
+
+            {import}"
+        };
+
+        // generic classes can not contain native imports
+        // so generic handles get a separate non generic class to hold their data
+        match s.generics.len() {
+            0 => {
+                let members = indent_by(4, format!("{handle_members}\n\n{holder_members}"));
                 formatdoc! {"
-                    
+                    {docs}public sealed{partial} class {name} : SafeHandle{interface}
                     {{
-                        unsafe
-                        {{
-                            fixed (Data* __polygen_self_ptr = &_data)
-                            {{
-                                return {convert_call};
-                            }}
-                        }}
+                        {members}
                     }}"
                 }
             }
-            _ => format!("=> {convert_call};"),
-        };
+            _ => {
+                let generics = utils::join(s.generics.iter(), ", ", |g| g.ident.to_string());
+                let handle_members = indent_by(4, handle_members);
+                let holder_members = indent_by(4, holder_members);
+                formatdoc! {"
+                    public static{partial} class {name}
+                    {{
+                        {holder_members}
+                    }}
 
-        formatdoc! {"
-            [DllImport(\"{lib_name}\", CallingConvention = CallingConvention.Cdecl)]
-            private static unsafe extern {out_data} {export_name}({export_params});
-            public{static_keyword} {out_type} {name}({func_params}) {conversion}"
+                    {docs}public sealed class {name}<{generics}> : SafeHandle
+                    {{
+                        {handle_members}
+                    }}"
+                }
+            }
+        }
+    }
+}
+
+fn render_constant(c: &PolyConst) -> String {
+    let name = c.name.to_pascal_case();
+    let ty = render_typename(Some(&c.ty));
+    let value = render_value(&c.ty, &c.value);
+    let docs = render_docs(&c.docs, &[]);
+    format!("{docs}public const {ty} {name} = {value};")
+}
+
+/// Renders doc comments as xml documentation, followed by a new line if there are any.
+fn render_docs(docs: &str, inputs: &[FnInput]) -> String {
+    let mut lines = Vec::new();
+    if !docs.is_empty() {
+        lines.push("<summary>".to_string());
+        lines.extend(docs.lines().map(escape_xml));
+        lines.push("</summary>".to_string());
+    }
+
+    for i in inputs.iter().filter(|i| !i.docs.is_empty()) {
+        let name = i.name.to_lower_camel_case();
+        let docs = escape_xml(&i.docs.replace('\n', " "));
+        lines.push(format!("<param name=\"{name}\">{docs}</param>"));
+    }
+
+    utils::join(lines.iter(), "", |l| match l.is_empty() {
+        true => "///\n".to_string(),
+        false => format!("/// {l}\n"),
+    })
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Renders the delegate type used to create callbacks with the given signature.
+fn render_delegate(name: &str, f: &PolyFnPtr) -> String {
+    let output = render_typename_data(f.output.as_deref());
+    let params = utils::join(f.inputs.iter().enumerate(), ", ", |(i, t)| {
+        format!("{} arg{i}", render_typename_data(Some(t)))
+    });
+    formatdoc! {"
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public unsafe delegate {output} {name}({params});"
+    }
+}
+
+/// Collects the signature of every function pointer that is used as a generic argument.
+fn collect_delegates(m: &PolyMod, delegates: &mut Vec<(String, PolyFnPtr)>) {
+    for (s, _) in m.structs() {
+        for g in &s.generics {
+            let PolyType::FnPtr(f) = &g.ty else {
+                continue;
+            };
+
+            let name = render_delegate_name(f);
+            if !delegates.iter().any(|(n, _)| *n == name) {
+                delegates.push((name, f.clone()));
+            }
         }
     }
+
+    for m in m.modules() {
+        collect_delegates(m, delegates);
+    }
+}
+
+/// Generic fields have no fixed offset, so only structs without them use the recorded layout.
+fn has_explicit_layout(s: &PolyStruct) -> bool {
+    s.fields.iter().all(|f| matches!(f.ty, FieldType::Typed(_)))
+}
+
+fn render_layout(s: &PolyStruct) -> String {
+    match has_explicit_layout(s) {
+        true => format!("[StructLayout(LayoutKind.Explicit, Size = {})]", s.size),
+        false => "[StructLayout(LayoutKind.Sequential)]".to_string(),
+    }
+}
+
+fn unsafe_keyword(params: &FnParams) -> &'static str {
+    let is_pointer = |t: &PolyType| matches!(t, PolyType::Pointer(_) | PolyType::FnPtr(_));
+    match params.inputs.iter().any(|i| is_pointer(&i.ty)) || params.output.iter().any(is_pointer) {
+        false => "",
+        true => " unsafe",
+    }
+}
+
+/// Renders the body of a function that calls into native code.
+///
+/// Handles passed to the function are protected from being released while the call is running.
+fn render_body(params: &FnParams, call: &str, fixed_self: bool) -> String {
+    let handles = params
+        .inputs
+        .iter()
+        .filter(|i| match &i.ty {
+            PolyType::Pointer(t) if i.name == "self" => is_handle_type(t),
+            t => is_handle_type(t),
+        })
+        .map(|i| {
+            let name = match i.name.as_str() {
+                "self" => "this".to_string(),
+                name => name.to_lower_camel_case(),
+            };
+            (name, i.consumed)
+        })
+        .collect::<Vec<_>>();
+
+    if handles.is_empty() && !fixed_self {
+        return format!(" => {call};");
+    }
+
+    let mut statement = match params.output {
+        None => format!("{call};"),
+        Some(_) => format!("return {call};"),
+    };
+
+    if fixed_self {
+        let inner = indent_by(8, statement);
+        statement = formatdoc! {"
+            unsafe
+            {{
+                fixed (Data* __polygen_self_ptr = &_data)
+                {{
+                    {inner}
+                }}
+            }}"
+        };
+    }
+
+    if !handles.is_empty() {
+        let added = utils::join(handles.iter(), "\n", |(h, _)| {
+            format!("bool __polygen_{h}_added = false;")
+        });
+        let add_ref = indent_by(
+            4,
+            utils::join(handles.iter(), "\n", |(h, _)| {
+                format!("{h}.DangerousAddRef(ref __polygen_{h}_added);")
+            }),
+        );
+        // handles whose value was moved into the native call are disposed, so later use throws
+        let release = indent_by(
+            4,
+            utils::join(handles.iter(), "\n", |(h, consumed)| match consumed {
+                false => format!("if (__polygen_{h}_added) {h}.DangerousRelease();"),
+                true => formatdoc! {"
+                    if (__polygen_{h}_added)
+                    {{
+                        {h}.DangerousRelease();
+                        {h}.Dispose();
+                    }}"
+                },
+            }),
+        );
+        let inner = indent_by(4, statement);
+        statement = formatdoc! {"
+            {added}
+            try
+            {{
+                {add_ref}
+                {inner}
+            }}
+            finally
+            {{
+                {release}
+            }}"
+        };
+    }
+
+    let statement = indent_by(4, statement);
+    formatdoc! {"
+        
+        {{
+            {statement}
+        }}"
+    }
 }
@@ -0,0 +1,5 @@
+mod polytype;
+mod render;
+mod utils;
+
+pub use render::*;
@@ -0,0 +1,92 @@
+use heck::ToPascalCase;
+use polygen::{
+    ir::{PolyStruct, PolyType, PolyValue, StructKind},
+    PolyBag,
+};
+
+use crate::utils;
+
+/// Returns the path of modules a struct lives in, excluding the crate name.
+pub fn module_path(s: &PolyStruct) -> Vec<String> {
+    s.module
+        .split("::")
+        .skip(1)
+        .map(|m| m.to_pascal_case())
+        .collect()
+}
+
+pub fn render_mangled(t: &PolyType) -> String {
+    match t {
+        PolyType::Primitive(p) => p.to_string(),
+        PolyType::Struct(s) => render_classname(s),
+        PolyType::Pointer(t) => format!("{}Ptr", render_mangled(t)),
+        PolyType::FnPtr(f) => {
+            let inputs = utils::join(f.inputs.iter(), "", |t| format!("_{}", render_mangled(t)));
+            match &f.output {
+                None => format!("Fn{inputs}"),
+                Some(output) => format!("Fn{inputs}_Returns_{}", render_mangled(output)),
+            }
+        }
+    }
+}
+
+/// Renders the unique top level class name used for a struct.
+pub fn render_classname(s: &PolyStruct) -> String {
+    let mut modules = utils::join(module_path(s).iter(), "_", |m| m.clone());
+    if !modules.is_empty() {
+        modules = format!("{modules}_");
+    }
+
+    let name = s.name.to_pascal_case();
+    let generics = utils::join(s.generics.iter(), "", |g| {
+        format!("_{}", render_mangled(&g.ty))
+    });
+    format!("{modules}{name}{generics}")
+}
+
+/// Renders the runtime descriptor used to read and write a type in memory.
+pub fn render_descriptor(t: Option<&PolyType>) -> String {
+    match t {
+        None => "null".to_string(),
+        Some(PolyType::Pointer(_) | PolyType::FnPtr(_)) => "types.pointer".to_string(),
+        Some(PolyType::Primitive(p)) => format!("types.{p}"),
+        Some(PolyType::Struct(s)) => render_classname(s),
+    }
+}
+
+/// Renders the type used in doc comments for a type.
+pub fn render_doc_type(t: &PolyType) -> String {
+    match t {
+        PolyType::Primitive(p) if p == "u64" || p == "i64" => "bigint".to_string(),
+        PolyType::Primitive(p) if p == "bool" => "boolean".to_string(),
+        PolyType::Primitive(_) | PolyType::Pointer(_) | PolyType::FnPtr(_) => "number".to_string(),
+        PolyType::Struct(s) => render_classname(s),
+    }
+}
+
+/// Collects every struct reachable from the bag.
+pub fn collect_structs(bag: &PolyBag) -> Vec<PolyStruct> {
+    // trait objects have no interfaces in javascript, so vtables are only needed where they are used
+    bag.root_module()
+        .reachable_structs(|s| !matches!(s.kind, StructKind::VTable(_)))
+}
+
+/// Renders a constant value as a javascript literal.
+pub fn render_value(ty: &PolyType, value: &PolyValue) -> String {
+    // 64 bit integers are read as bigints, so their constants are bigints too
+    let bigint = matches!(ty, PolyType::Primitive(p) if p == "u64" || p == "i64");
+    match value {
+        PolyValue::Bool(v) => v.to_string(),
+        PolyValue::Int(v) if bigint => format!("{v}n"),
+        PolyValue::UInt(v) if bigint => format!("{v}n"),
+        PolyValue::Int(v) => v.to_string(),
+        PolyValue::UInt(v) => v.to_string(),
+        PolyValue::Float(v) if v.is_nan() => "NaN".to_string(),
+        PolyValue::Float(v) if v.is_infinite() && *v > 0.0 => "Infinity".to_string(),
+        PolyValue::Float(v) if v.is_infinite() => "-Infinity".to_string(),
+        PolyValue::Float(v) => match ty {
+            PolyType::Primitive(p) if p == "f32" => format!("{:?}", *v as f32),
+            _ => format!("{v:?}"),
+        },
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+
+use crate::{
+    polytype::{
+        collect_structs, module_path, render_classname, render_descriptor, render_doc_type,
+        render_value,
+    },
+    utils,
+};
+
+use heck::{ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase};
+use indent::indent_by;
+use indoc::{formatdoc, indoc};
+use polygen::{
+    ir::{FnParams, ImplFn, PolyFn, PolyImpl, PolyStruct, PolyType, StructField, StructKind},
+    validate::ProblemKind,
+    PolyBag, PolyMod,
+};
+use thiserror::Error;
+
+static RUNTIME: &str = indoc! {r#"
+    let instance = null;
+
+    export function init(wasmInstance) {
+        instance = wasmInstance;
+    }
+
+    export async function load(source, imports = {}) {
+        const result = await WebAssembly.instantiate(source, imports);
+        init(result.instance);
+        return result.instance;
+    }
+
+    export function alloc(size, align) {
+        return exports().__polygen_alloc(size, align);
+    }
+
+    export function free(ptr, size, align) {
+        exports().__polygen_free(ptr, size, align);
+    }
+
+    function exports() {
+        if (instance === null) {
+            throw new Error("the wasm instance has not been initialized");
+        }
+
+        return instance.exports;
+    }
+
+    function view() {
+        return new DataView(exports().memory.buffer);
+    }
+
+    function primitive(size, get, set) {
+        return {
+            $size: size,
+            $align: size,
+            $read: (view, ptr) => view[get](ptr, true),
+            $write: (view, ptr, value) => view[set](ptr, value, true),
+        };
+    }
+
+    const types = {
+        u8: primitive(1, "getUint8", "setUint8"),
+        u16: primitive(2, "getUint16", "setUint16"),
+        u32: primitive(4, "getUint32", "setUint32"),
+        u64: primitive(8, "getBigUint64", "setBigUint64"),
+        usize: primitive(4, "getUint32", "setUint32"),
+        i8: primitive(1, "getInt8", "setInt8"),
+        i16: primitive(2, "getInt16", "setInt16"),
+        i32: primitive(4, "getInt32", "setInt32"),
+        i64: primitive(8, "getBigInt64", "setBigInt64"),
+        isize: primitive(4, "getInt32", "setInt32"),
+        f32: primitive(4, "getFloat32", "setFloat32"),
+        f64: primitive(8, "getFloat64", "setFloat64"),
+        bool: {
+            $size: 1,
+            $align: 1,
+            $read: (view, ptr) => view.getUint8(ptr) !== 0,
+            $write: (view, ptr, value) => view.setUint8(ptr, value ? 1 : 0),
+        },
+        pointer: primitive(4, "getUint32", "setUint32"),
+    };
+
+    // passes a value by reference and copies any changes back into it after the call
+    function ref(type) {
+        return {
+            $size: type.$size,
+            $align: type.$align,
+            $read: (view, ptr) => type.$read(view, ptr),
+            $write: (view, ptr, value) => type.$write(view, ptr, value),
+            $byRef: true,
+        };
+    }
+
+    // every export takes its arguments by pointer and writes its output through a pointer
+    function call(symbol, output, inputs, args) {
+        const wasm = exports();
+        const ptrs = inputs.map((type) => wasm.__polygen_alloc(type.$size, type.$align));
+        const out = output === null ? null : wasm.__polygen_alloc(output.$size, output.$align);
+        try {
+            inputs.forEach((type, i) => type.$write(view(), ptrs[i], args[i]));
+            if (out === null) {
+                wasm[symbol](...ptrs);
+            } else {
+                wasm[symbol](out, ...ptrs);
+            }
+
+            inputs.forEach((type, i) => {
+                if (type.$byRef) {
+                    Object.assign(args[i], type.$read(view(), ptrs[i]));
+                }
+            });
+
+            return out === null ? undefined : output.$read(view(), out);
+        } finally {
+            inputs.forEach((type, i) => wasm.__polygen_free(ptrs[i], type.$size, type.$align));
+            if (out !== null) {
+                wasm.__polygen_free(out, output.$size, output.$align);
+            }
+        }
+    }"#
+};
+
+#[derive(Debug, Error)]
+pub enum JsError {
+    #[error("`{0}` is a trait implemented by foreign code, which is not supported by javascript")]
+    ForeignTrait(String),
+    #[error("The primitive `{0}` has no javascript equivalent")]
+    UnsupportedPrimitive(String),
+}
+
+pub struct JsRenderer;
+
+impl JsRenderer {
+    /// Renders the glue for a bag, which has to be registered by the `wasm32` build of the library.
+    ///
+    /// Structs are read and written at the offsets recorded in the bag, so a bag from a native
+    /// build only works as long as its layouts match the ones on `wasm32`.
+    pub fn render(&self, bag: &PolyBag) -> Result<String, JsError> {
+        // the runtime only has descriptors for the supported primitives
+        let unsupported = bag.validate().into_iter().find_map(|p| match p.kind {
+            ProblemKind::UnsupportedType(t) => Some(t),
+            _ => None,
+        });
+        if let Some(t) = unsupported {
+            return Err(JsError::UnsupportedPrimitive(t));
+        }
+
+        let structs = collect_structs(bag);
+
+        // wasm function tables can not be filled from javascript, so vtables can not be implemented
+        let vtable = structs
+            .iter()
+            .find(|s| matches!(s.kind, StructKind::VTable(_)));
+        if let Some(s) = vtable {
+            return Err(JsError::ForeignTrait(format!("{}::{}", s.module, s.name)));
+        }
+
+        let mut impls = HashMap::new();
+        collect_impls(bag.root_module(), &mut impls);
+
+        let classes = utils::join(structs.iter(), "\n\n", |s| {
+            self.render_struct(s, impls.get(&render_classname(s)).copied())
+        });
+
+        // place every struct in the module it was defined in
+        let mut root = JsModule::new(bag.root_module().name());
+        for s in &structs {
+            let name = s.name.to_pascal_case();
+            let classname = render_classname(s);
+            root.get_target_mod(&module_path(s))
+                .items
+                .push(format!("{name}: {classname},"));
+        }
+
+        root.insert_items(self, bag.root_module());
+
+        let name = bag.root_module().name().to_pascal_case();
+        let module = root.render();
+
+        Ok(formatdoc! {"
+            {RUNTIME}
+
+            {classes}
+
+            export const {name} = {module};
+            "
+        })
+    }
+
+    fn render_struct(&self, s: &PolyStruct, i: Option<&PolyImpl>) -> String {
+        let name = render_classname(s);
+        let size = s.size;
+        let align = s.align;
+
+        let properties = utils::join(s.fields.iter(), ", ", render_property);
+        let documented = s.fields.iter().filter(|f| f.visible && !f.docs.is_empty());
+        let tags = documented.map(|f| {
+            let ty = render_doc_type(s.resolve_field(&f.ty));
+            let name = render_property(f);
+            format!("@property {{{ty}}} {name} {}", f.docs.replace('\n', " "))
+        });
+        let docs = render_docs(&s.docs, tags.collect());
+        let assign = indent_by(
+            8,
+            utils::join(s.fields.iter(), "\n", |f| {
+                let property = render_property(f);
+                format!("this.{property} = {property};")
+            }),
+        );
+
+        let read = indent_by(
+            12,
+            utils::join(s.fields.iter(), "\n", |f| {
+                let property = render_property(f);
+                let ty = render_descriptor(Some(s.resolve_field(&f.ty)));
+                format!("{property}: {ty}.$read(view, ptr + {}),", f.offset)
+            }),
+        );
+
+        let mut write = utils::join(s.fields.iter(), "\n", |f| {
+            let property = render_property(f);
+            let ty = render_descriptor(Some(s.resolve_field(&f.ty)));
+            format!("{ty}.$write(view, ptr + {}, value.{property});", f.offset)
+        });
+
+        // released handles must not reach native code
+        if let StructKind::Handle(_) = &s.kind {
+            write = format!("{}\n{write}", released_guard("value"));
+        }
+        let write = indent_by(8, write);
+
+        let constants = utils::join(i.iter().flat_map(|i| &i.constants), "", |c| {
+            let name = c.name.to_shouty_snake_case();
+            let value = render_value(&c.ty, &c.value);
+            let docs = render_docs(&c.docs, Vec::new());
+            format!("\n{docs}static {name} = {value};")
+        });
+        let constants = indent_by(4, constants);
+
+        let mut methods = match i {
+            None => String::new(),
+            Some(i) => {
+                let mut methods = String::new();
+                for f in &i.functions {
+                    methods += "\n\n";
+                    methods += &self.render_struct_function(s, f);
+                }
+                indent_by(4, methods)
+            }
+        };
+
+        // handles are not garbage collected, so they are released explicitly
+        if let StructKind::Handle(_) = &s.kind {
+            let docs = render_docs(
+                "Releases the native value, after which the handle must not be used anymore.",
+                Vec::new(),
+            );
+            let guard = indent_by(4, released_guard("this"));
+            let release = formatdoc! {"
+                {docs}release() {{
+                    {guard}
+                    exports().__polygen_drop(this._ptr, this._drop);
+                    this._ptr = 0;
+                }}"
+            };
+            methods += &indent_by(4, format!("\n\n{release}"));
+        }
+
+        formatdoc! {"
+            {docs}class {name} {{
+                static $size = {size};
+                static $align = {align};{constants}
+
+                constructor({{ {properties} }} = {{}}) {{
+                    {assign}
+                }}
+
+                static $read(view, ptr) {{
+                    return new {name}({{
+                        {read}
+                    }});
+                }}
+
+                static $write(view, ptr, value) {{
+                    {write}
+                }}{methods}
+            }}"
+        }
+    }
+
+    fn render_function(&self, f: &PolyFn) -> String {
+        let name = f.name.to_lower_camel_case();
+        let params = render_params(&f.params);
+        let call = render_call(&f.export_name, &f.params, |i| {
+            (render_descriptor(Some(&i.ty)), i.name.to_lower_camel_case())
+        });
+
+        let body = indent_by(4, then(call, release_consumed(&f.params)));
+        let docs = render_docs(&f.docs, param_tags(&f.params));
+        formatdoc! {"
+            {docs}{name}({params}) {{
+                {body}
+            }},"
+        }
+    }
+
+    fn render_struct_function(&self, s: &PolyStruct, f: &ImplFn) -> String {
+        let name = f.name.to_lower_camel_case();
+        let params = render_params(&f.params);
+        let static_keyword = match f.params.inputs.iter().any(|i| i.name == "self") {
+            false => "static ",
+            true => "",
+        };
+
+        let call = render_call(&f.export_name, &f.params, |i| match &i.ty {
+            PolyType::Pointer(_) if i.name == "self" => {
+                let classname = render_classname(s);
+                (format!("ref({classname})"), "this".to_string())
+            }
+            ty if i.name == "self" => (render_descriptor(Some(ty)), "this".to_string()),
+            ty => (render_descriptor(Some(ty)), i.name.to_lower_camel_case()),
+        });
+
+        let body = indent_by(4, then(call, release_consumed(&f.params)));
+        let docs = render_docs(&f.docs, param_tags(&f.params));
+        formatdoc! {"
+            {docs}{static_keyword}{name}({params}) {{
+                {body}
+            }}"
+        }
+    }
+}
+
+fn render_property(f: &StructField) -> String {
+    let name = f.name.to_lower_camel_case();
+    match f.visible {
+        false => format!("_{name}"),
+        true => name,
+    }
+}
+
+fn param_tags(params: &FnParams) -> Vec<String> {
+    let documented = params.inputs.iter();
+    let documented = documented.filter(|i| i.name != "self" && !i.docs.is_empty());
+    let tags = documented.map(|i| {
+        let ty = render_doc_type(&i.ty);
+        let name = i.name.to_lower_camel_case();
+        format!("@param {{{ty}}} {name} {}", i.docs.replace('\n', " "))
+    });
+    tags.collect()
+}
+
+/// Renders doc comments as a jsdoc block, followed by a new line if there are any.
+fn render_docs(docs: &str, tags: Vec<String>) -> String {
+    let mut lines = docs.lines().map(ToString::to_string).collect::<Vec<_>>();
+    if !lines.is_empty() && !tags.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines.extend(tags);
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    // a closing comment inside of the docs would end the block early
+    let lines = utils::join(lines.iter(), "\n", |l| match l.is_empty() {
+        true => " *".to_string(),
+        false => format!(" * {}", l.replace("*/", "*\\/")),
+    });
+    format!("/**\n{lines}\n */\n")
+}
+
+fn render_params(params: &FnParams) -> String {
+    utils::join(
+        params.inputs.iter().filter(|i| i.name != "self"),
+        ", ",
+        |i| i.name.to_lower_camel_case(),
+    )
+}
+
+fn released_guard(handle: &str) -> String {
+    formatdoc! {"
+        if (!{handle}._ptr) {{
+            throw new Error(\"The handle was released or moved into a native call\");
+        }}"
+    }
+}
+
+/// Returns the result of a call after running the statements that follow it.
+fn then(call: String, after: Vec<String>) -> String {
+    match after.is_empty() {
+        true => format!("return {call};"),
+        false => format!(
+            "const $result = {call};\n{}\nreturn $result;",
+            after.join("\n")
+        ),
+    }
+}
+
+/// Releases the handles whose native value was moved into the call.
+fn release_consumed(params: &FnParams) -> Vec<String> {
+    let consumed = params.inputs.iter().filter(|i| i.consumed);
+    let consumed = consumed.map(|i| match i.name.as_str() {
+        "self" => "this.release();".to_string(),
+        name => format!("{}.release();", name.to_lower_camel_case()),
+    });
+    consumed.collect()
+}
+
+fn render_call(
+    export_name: &str,
+    params: &FnParams,
+    mut input: impl FnMut(&polygen::ir::FnInput) -> (String, String),
+) -> String {
+    let output = render_descriptor(params.output.as_ref());
+    let (types, args): (Vec<_>, Vec<_>) = params.inputs.iter().map(&mut input).unzip();
+    let types = types.join(", ");
+    let args = args.join(", ");
+    format!("call(\"{export_name}\", {output}, [{types}], [{args}])")
+}
+
+fn collect_impls<'a>(m: &'a PolyMod, impls: &mut HashMap<String, &'a PolyImpl>) {
+    for (s, i) in m.structs() {
+        if let Some(i) = i {
+            impls.insert(render_classname(s), i);
+        }
+    }
+
+    for m in m.modules() {
+        collect_impls(m, impls);
+    }
+}
+
+/// A module rendered as a nested javascript object.
+struct JsModule {
+    name: String,
+    items: Vec<String>,
+    modules: Vec<JsModule>,
+}
+
+impl JsModule {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_pascal_case(),
+            items: Vec::new(),
+            modules: Vec::new(),
+        }
+    }
+
+    fn get_target_mod(&mut self, path: &[String]) -> &mut JsModule {
+        let mut target_mod = self;
+        for name in path {
+            let index = match target_mod.modules.iter().position(|m| m.name == *name) {
+                Some(index) => index,
+                None => {
+                    target_mod.modules.push(JsModule::new(name));
+                    target_mod.modules.len() - 1
+                }
+            };
+            target_mod = &mut target_mod.modules[index];
+        }
+
+        target_mod
+    }
+
+    fn insert_items(&mut self, renderer: &JsRenderer, m: &PolyMod) {
+        for c in m.constants() {
+            let name = c.name.to_shouty_snake_case();
+            let value = render_value(&c.ty, &c.value);
+            let docs = render_docs(&c.docs, Vec::new());
+            self.items.push(format!("{docs}{name}: {value},"));
+        }
+
+        for f in m.functions() {
+            self.items.push(renderer.render_function(f));
+        }
+
+        for m in m.modules() {
+            let name = m.name().to_pascal_case();
+            self.get_target_mod(&[name]).insert_items(renderer, m);
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut items = self.items.clone();
+        for m in &self.modules {
+            items.push(format!("{}: {},", m.name, m.render()));
+        }
+
+        let items = indent_by(4, items.join("\n"));
+        formatdoc! {"
+            {{
+                {items}
+            }}"
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+
+use polygen::{
+    ir::{FnParams, PolyFnPtr, PolyStruct, PolyType},
+    PolyBag, PolyMod,
+};
+
+use crate::{
+    polytype::{render_c_fn_typename, render_c_structname, render_c_typename},
+    utils,
+};
+
+/// Collects every struct reachable from the bag.
+///
+/// The structs are ordered so that any struct used by value in
+/// another struct always comes before the struct that uses it.
+pub fn collect_structs(bag: &PolyBag) -> Vec<PolyStruct> {
+    let found = bag.root_module().reachable_structs(|_| true);
+
+    let mut sorted = Vec::with_capacity(found.len());
+    let mut emitted = HashSet::new();
+    for s in &found {
+        sort_struct(s, &mut sorted, &mut emitted);
+    }
+
+    sorted
+}
+
+fn sort_struct(s: &PolyStruct, sorted: &mut Vec<PolyStruct>, emitted: &mut HashSet<String>) {
+    if !emitted.insert(render_c_structname(s)) {
+        return;
+    }
+
+    // only structs used by value have to be defined first
+    for field in &s.fields {
+        if let PolyType::Struct(field_struct) = s.resolve_field(&field.ty) {
+            sort_struct(field_struct, sorted, emitted);
+        }
+    }
+
+    sorted.push(s.clone());
+}
+
+/// Renders the C declarations used for `FFI::cdef`.
+pub fn render_cdef(bag: &PolyBag, structs: &[PolyStruct]) -> String {
+    let typedefs = utils::join(structs.iter(), "\n", |s| {
+        let name = render_c_structname(s);
+        format!("typedef struct {name} {name};")
+    });
+
+    let mut pointers = Vec::new();
+    for s in structs {
+        for f in &s.fields {
+            collect_fn_pointers(s.resolve_field(&f.ty), &mut pointers);
+        }
+    }
+
+    let definitions = utils::join(structs.iter(), "\n\n", |s| {
+        let name = render_c_structname(s);
+        let fields = utils::join(s.fields.iter(), "\n", |f| {
+            let ty = render_c_typename(Some(s.resolve_field(&f.ty)));
+            format!("    {ty} {};", f.name)
+        });
+
+        format!("struct {name} {{\n{fields}\n}};")
+    });
+
+    let mut functions = Vec::new();
+    collect_functions(bag.root_module(), &mut functions, &mut pointers);
+    functions.push("uint64_t __polygen_abi_fingerprint(const char *name, size_t len);".to_string());
+    functions.push("void __polygen_drop(uintptr_t ptr, void (*destructor)(void *));".to_string());
+
+    // function pointers are declared after the struct typedefs they may refer to
+    let pointers = utils::join(pointers.iter(), "\n", |f| {
+        let name = render_c_fn_typename(f);
+        let output = render_c_typename(f.output.as_deref());
+        let mut inputs = utils::join(f.inputs.iter(), ", ", |t| render_c_typename(Some(t)));
+        if inputs.is_empty() {
+            inputs = "void".to_string();
+        }
+
+        format!("typedef {output} (*{name})({inputs});")
+    });
+
+    let mut output = typedefs;
+    for section in [pointers, definitions, functions.join("\n")] {
+        if !output.is_empty() && !section.is_empty() {
+            output += "\n\n";
+        }
+
+        output += &section;
+    }
+
+    output
+}
+
+fn collect_functions(m: &PolyMod, functions: &mut Vec<String>, pointers: &mut Vec<PolyFnPtr>) {
+    let impl_params = m
+        .structs()
+        .flat_map(|(_, i)| i.into_iter().flat_map(|i| &i.functions))
+        .map(|f| (&f.export_name, &f.params));
+    let params = m.functions().map(|f| (&f.export_name, &f.params));
+
+    for (export_name, params) in impl_params.chain(params) {
+        for t in params.inputs.iter().map(|i| &i.ty).chain(&params.output) {
+            collect_fn_pointers(t, pointers);
+        }
+
+        functions.push(render_function_decl(export_name, params));
+    }
+
+    for m in m.modules() {
+        collect_functions(m, functions, pointers);
+    }
+}
+
+/// Collects every function pointer signature used by a type, nested signatures first.
+fn collect_fn_pointers(t: &PolyType, found: &mut Vec<PolyFnPtr>) {
+    match t {
+        PolyType::Pointer(t) => collect_fn_pointers(t, found),
+        PolyType::FnPtr(f) => {
+            for t in f.inputs.iter().chain(f.output.as_deref()) {
+                collect_fn_pointers(t, found);
+            }
+
+            if !found.contains(f) {
+                found.push(f.clone());
+            }
+        }
+        PolyType::Primitive(_) | PolyType::Struct(_) => (),
+    }
+}
+
+fn render_function_decl(export_name: &str, params: &FnParams) -> String {
+    let output = render_c_typename(params.output.as_ref());
+    let mut inputs = utils::join(params.inputs.iter(), ", ", |i| {
+        let ty = render_c_typename(Some(&i.ty));
+        format!("{ty} {}", i.name)
+    });
+
+    if inputs.is_empty() {
+        inputs = "void".to_string();
+    }
+
+    format!("{output} {export_name}({inputs});")
+}
@@ -0,0 +1,6 @@
+mod cdef;
+mod polytype;
+mod render;
+mod utils;
+
+pub use render::*;
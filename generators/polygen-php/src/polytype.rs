@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use heck::ToPascalCase;
+use once_cell::sync::Lazy;
+use polygen::ir::{PolyFnPtr, PolyStruct, PolyType, PolyValue};
+
+use crate::utils;
+
+static C_PRIMITIVES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
+    HashMap::from([
+        ("u8", "uint8_t"),
+        ("u16", "uint16_t"),
+        ("u32", "uint32_t"),
+        ("u64", "uint64_t"),
+        ("usize", "uintptr_t"),
+        ("i8", "int8_t"),
+        ("i16", "int16_t"),
+        ("i32", "int32_t"),
+        ("i64", "int64_t"),
+        ("isize", "intptr_t"),
+        ("bool", "bool"),
+        ("f32", "float"),
+        ("f64", "double"),
+        ("c_void", "void"),
+    ])
+});
+
+static PHP_PRIMITIVES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
+    HashMap::from([
+        ("u8", "int"),
+        ("u16", "int"),
+        ("u32", "int"),
+        ("u64", "int"),
+        ("usize", "int"),
+        ("i8", "int"),
+        ("i16", "int"),
+        ("i32", "int"),
+        ("i64", "int"),
+        ("isize", "int"),
+        ("bool", "bool"),
+        ("f32", "float"),
+        ("f64", "float"),
+    ])
+});
+
+fn render_primitive(primitives: &HashMap<&str, &str>, p: &str) -> String {
+    let primitive = primitives.get(p);
+    primitive
+        .expect("unsupported primitives are rejected by render")
+        .to_string()
+}
+
+/// Returns the path of modules a struct lives in, excluding the crate name.
+pub fn module_path(s: &PolyStruct) -> Vec<String> {
+    s.module
+        .split("::")
+        .skip(1)
+        .map(|m| m.to_pascal_case())
+        .collect()
+}
+
+pub fn render_mangled(t: &PolyType) -> String {
+    match t {
+        PolyType::Primitive(p) => p.to_string(),
+        PolyType::Struct(s) => render_c_structname(s),
+        PolyType::Pointer(t) => format!("{}Ptr", render_mangled(t)),
+        PolyType::FnPtr(f) => render_c_fn_typename(f),
+    }
+}
+
+/// Renders the name of the typedef declared for a function pointer signature.
+pub fn render_c_fn_typename(f: &PolyFnPtr) -> String {
+    let inputs = utils::join(f.inputs.iter(), "", |t| format!("_{}", render_mangled(t)));
+    match &f.output {
+        None => format!("Fn{inputs}"),
+        Some(output) => format!("Fn{inputs}_Returns_{}", render_mangled(output)),
+    }
+}
+
+pub fn render_generic_suffix(s: &PolyStruct) -> String {
+    utils::join(s.generics.iter(), "", |g| {
+        format!("_{}", render_mangled(&g.ty))
+    })
+}
+
+pub fn render_c_structname(s: &PolyStruct) -> String {
+    let mut modules = utils::join(module_path(s).iter(), "_", |m| m.clone());
+    if !modules.is_empty() {
+        modules = format!("{modules}_");
+    }
+
+    let name = s.name.to_pascal_case();
+    let generics = render_generic_suffix(s);
+    format!("{modules}{name}{generics}")
+}
+
+pub fn render_c_typename(t: Option<&PolyType>) -> String {
+    match t {
+        None => "void".to_string(),
+        Some(PolyType::Pointer(t)) => format!("{}*", render_c_typename(Some(t))),
+        Some(PolyType::Primitive(p)) => render_primitive(&C_PRIMITIVES, p),
+        Some(PolyType::Struct(s)) => render_c_structname(s),
+        Some(PolyType::FnPtr(f)) => render_c_fn_typename(f),
+    }
+}
+
+pub fn render_php_classname(s: &PolyStruct) -> String {
+    let name = s.name.to_pascal_case();
+    let generics = render_generic_suffix(s);
+    format!("{name}{generics}")
+}
+
+pub fn render_php_namespace(base: &str, s: &PolyStruct) -> String {
+    let mut namespace = base.to_string();
+    for module in module_path(s) {
+        namespace += "\\";
+        namespace += &module;
+    }
+
+    namespace
+}
+
+pub fn render_php_typename(base: &str, t: Option<&PolyType>) -> String {
+    match t {
+        None => "void".to_string(),
+        Some(PolyType::Pointer(_)) => "\\FFI\\CData".to_string(),
+        // ffi converts closures into native function pointers
+        Some(PolyType::FnPtr(_)) => "\\FFI\\CData|\\Closure".to_string(),
+        Some(PolyType::Primitive(p)) => render_primitive(&PHP_PRIMITIVES, p),
+        Some(PolyType::Struct(s)) => {
+            let namespace = render_php_namespace(base, s);
+            let name = render_php_classname(s);
+            format!("\\{namespace}\\{name}")
+        }
+    }
+}
+
+/// Renders a constant value as a php literal.
+pub fn render_value(ty: &PolyType, value: &PolyValue) -> String {
+    match value {
+        PolyValue::Bool(v) => v.to_string(),
+        PolyValue::Int(v) => v.to_string(),
+        // php integers are signed, just like the values php reads through ffi
+        PolyValue::UInt(v) => (*v as i64).to_string(),
+        PolyValue::Float(v) if v.is_nan() => "NAN".to_string(),
+        PolyValue::Float(v) if v.is_infinite() && *v > 0.0 => "INF".to_string(),
+        PolyValue::Float(v) if v.is_infinite() => "-INF".to_string(),
+        PolyValue::Float(v) => match ty {
+            PolyType::Primitive(p) if p == "f32" => format!("{:?}", *v as f32),
+            _ => format!("{v:?}"),
+        },
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use crate::{
+    cdef::{collect_structs, render_cdef},
+    polytype::{
+        render_c_structname, render_php_classname, render_php_namespace, render_php_typename,
+        render_value,
+    },
+    utils,
+};
+
+use heck::{ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase};
+use indent::indent_by;
+use indoc::{formatdoc, indoc};
+use polygen::{
+    ir::{
+        FnInput, FnParams, ImplFn, PolyConst, PolyFn, PolyImpl, PolyStruct, PolyType, StructKind,
+        TraitFn,
+    },
+    validate::ProblemKind,
+    PolyBag, PolyMod,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PhpError {
+    #[error("The type `{0}` has no PHP equivalent")]
+    UnsupportedType(String),
+}
+
+pub struct PhpRenderer {
+    pub lib_name: String,
+    pub namespace: String,
+}
+
+impl PhpRenderer {
+    pub fn render(&self, bag: &PolyBag) -> Result<String, PhpError> {
+        // primitives are looked up in a fixed table, which only has the supported ones
+        let unsupported = bag.validate().into_iter().find_map(|p| match p.kind {
+            ProblemKind::UnsupportedType(t) => Some(t),
+            _ => None,
+        });
+        if let Some(t) = unsupported {
+            return Err(PhpError::UnsupportedType(t));
+        }
+
+        let root = bag.root_module().name().to_pascal_case();
+        let base = format!("{}\\{root}", self.namespace);
+        let structs = collect_structs(bag);
+
+        let mut impls = HashMap::new();
+        collect_impls(bag.root_module(), &mut impls);
+
+        // group all items by the namespace they will be rendered in
+        let mut namespaces = Vec::<(String, Vec<String>)>::new();
+        let mut push_item = |namespace: String, item: String| match namespaces
+            .iter_mut()
+            .find(|(n, _)| *n == namespace)
+        {
+            Some((_, items)) => items.push(item),
+            None => namespaces.push((namespace, vec![item])),
+        };
+
+        push_item(
+            self.namespace.clone(),
+            self.render_loader(bag, &root, &structs),
+        );
+        for s in &structs {
+            let i = impls.get(&render_c_structname(s)).copied();
+            push_item(
+                render_php_namespace(&base, s),
+                self.render_struct(&base, s, i),
+            );
+        }
+
+        let mut constants = Vec::new();
+        collect_constants(bag.root_module(), base.clone(), &mut constants);
+        for (namespace, c) in constants {
+            push_item(namespace, render_constant(&base, c));
+        }
+
+        let mut functions = Vec::new();
+        collect_functions(bag.root_module(), base.clone(), &mut functions);
+        for (namespace, f) in functions {
+            push_item(namespace, self.render_function(&base, f));
+        }
+
+        let namespaces = utils::join(namespaces.iter(), "\n\n", |(namespace, items)| {
+            let items = indent_by(4, items.join("\n\n"));
+            formatdoc! {"
+                namespace {namespace}
+                {{
+                    {items}
+                }}"
+            }
+        });
+
+        Ok(formatdoc! {"
+            <?php
+
+            {namespaces}
+            "
+        })
+    }
+
+    fn render_loader(&self, bag: &PolyBag, root: &str, structs: &[PolyStruct]) -> String {
+        let lib_name = &self.lib_name;
+        let cdef = indent_by(8, render_cdef(bag, structs));
+
+        let fingerprints = bag.root_module().fingerprints();
+        let fingerprints = indent_by(
+            8,
+            utils::join(fingerprints.iter(), "\n", |(export_name, fingerprint)| {
+                // php integers are signed so the fingerprint is stored as its signed representation
+                format!("'{export_name}' => {},", *fingerprint as i64)
+            }),
+        );
+
+        formatdoc! {"
+            final class {root}
+            {{
+                public const CDEF = <<<'CDEF'
+                    {cdef}
+                    CDEF;
+
+                // the abi fingerprint of every function and struct the bindings were generated for
+                private const FINGERPRINTS = [
+                    {fingerprints}
+                ];
+
+                private static ?\\FFI $ffi = null;
+
+                public static function load(?string $library = null): \\FFI
+                {{
+                    $ffi = \\FFI::cdef(self::CDEF, $library ?? self::defaultLibrary());
+                    foreach (self::FINGERPRINTS as $export => $fingerprint) {{
+                        if ($ffi->__polygen_abi_fingerprint($export, strlen($export)) !== $fingerprint) {{
+                            throw new \\LogicException(\"the bindings are out of date, `$export` does not match the native library\");
+                        }}
+                    }}
+
+                    return self::$ffi = $ffi;
+                }}
+
+                public static function ffi(): \\FFI
+                {{
+                    return self::$ffi ?? self::load();
+                }}
+
+                private static function defaultLibrary(): string
+                {{
+                    return match (PHP_OS_FAMILY) {{
+                        'Windows' => '{lib_name}.dll',
+                        'Darwin' => 'lib{lib_name}.dylib',
+                        default => 'lib{lib_name}.so',
+                    }};
+                }}
+            }}"
+        }
+    }
+
+    fn render_struct(&self, base: &str, s: &PolyStruct, i: Option<&PolyImpl>) -> String {
+        let name = render_php_classname(s);
+        let c_name = render_c_structname(s);
+        let loader = loader_name(base);
+        let docs = render_docs(base, &s.docs, &[]);
+
+        let mut properties = utils::join(s.fields.iter(), "\n", |f| {
+            let vis = match f.visible {
+                false => "private",
+                true => "public",
+            };
+            let ty = render_php_typename(base, Some(s.resolve_field(&f.ty)));
+            let name = f.name.to_lower_camel_case();
+            let docs = render_docs(base, &f.docs, &[]);
+            format!("{docs}{vis} {ty} ${name};")
+        });
+
+        // constants of the impl are placed in front of the properties
+        let constants = i.iter().flat_map(|i| &i.constants);
+        let constants = utils::join(constants, "\n", |c| render_constant(base, c));
+        if !constants.is_empty() {
+            properties = format!("{constants}\n\n{properties}");
+        }
+        let properties = indent_by(4, properties);
+
+        let mut to_c = utils::join(s.fields.iter(), "\n", |f| {
+            let ty = s.resolve_field(&f.ty);
+            let value = convert_to_c(ty, &format!("$this->{}", f.name.to_lower_camel_case()));
+            format!("$data->{} = {value};", f.name)
+        });
+        to_c = format!("$data = {loader}::ffi()->new('{c_name}');\n{to_c}");
+
+        // released handles must not reach native code
+        if let StructKind::Handle(_) = &s.kind {
+            to_c = format!("{RELEASED_GUARD}\n{to_c}");
+        }
+        let to_c = indent_by(8, to_c);
+
+        let assign = indent_by(
+            8,
+            utils::join(s.fields.iter(), "\n", |f| {
+                let ty = s.resolve_field(&f.ty);
+                let value = convert_from_c(base, Some(ty), &format!("$data->{}", f.name));
+                format!("$this->{} = {value};", f.name.to_lower_camel_case())
+            }),
+        );
+
+        let mut methods = match (i, &s.kind) {
+            (_, StructKind::VTable(methods)) => {
+                indent_by(4, format!("\n\n{}", render_from_object(base, s, methods)))
+            }
+            (None, _) => String::new(),
+            (Some(i), _) => {
+                let mut methods = String::new();
+                for f in &i.functions {
+                    methods += "\n\n";
+                    methods += &self.render_struct_function(base, f);
+                }
+                indent_by(4, methods)
+            }
+        };
+
+        // php copies objects from native memory, so handles are released explicitly
+        if let StructKind::Handle(_) = &s.kind {
+            let docs = render_docs(
+                base,
+                "Releases the native value, after which the handle must not be used anymore.",
+                &[],
+            );
+            let guard = indent_by(4, RELEASED_GUARD);
+            let release = formatdoc! {"
+                {docs}public function release(): void
+                {{
+                    {guard}
+                    {loader}::ffi()->__polygen_drop($this->ptr, $this->drop);
+                    $this->ptr = 0;
+                }}"
+            };
+            methods += &indent_by(4, format!("\n\n{release}"));
+        }
+
+        // traits get an interface for the objects that implement them
+        let interface = match &s.kind {
+            StructKind::VTable(methods) => render_interface(base, s, methods),
+            _ => String::new(),
+        };
+
+        // trait objects implement the interface of their trait through their methods
+        let implements = match &s.kind {
+            StructKind::Handle(Some(t)) => {
+                let name = render_php_typename(base, Some(t));
+                format!(" implements {name}Interface")
+            }
+            _ => String::new(),
+        };
+
+        formatdoc! {"
+            {interface}{docs}class {name}{implements}
+            {{
+                {properties}
+
+                private function __construct()
+                {{
+                }}
+
+                public static function fromC(\\FFI\\CData $data): self
+                {{
+                    $self = new self();
+                    $self->assign($data);
+                    return $self;
+                }}
+
+                public function toC(): \\FFI\\CData
+                {{
+                    {to_c}
+                    return $data;
+                }}
+
+                private function assign(\\FFI\\CData $data): void
+                {{
+                    {assign}
+                }}{methods}
+            }}"
+        }
+    }
+
+    fn render_function(&self, base: &str, f: &PolyFn) -> String {
+        let name = f.name.to_lower_camel_case();
+        let params = render_params(base, &f.params);
+        let out_type = render_php_typename(base, f.params.output.as_ref());
+        let args = utils::join(f.params.inputs.iter(), ", ", |i| {
+            convert_to_c(&i.ty, &format!("${}", i.name.to_lower_camel_case()))
+        });
+
+        let call = render_call(base, &f.export_name, &args, &f.params);
+        let body = indent_by(4, then(call, release_consumed(&f.params)));
+        let docs = render_docs(base, &f.docs, &f.params.inputs);
+
+        formatdoc! {"
+            {docs}function {name}({params}): {out_type}
+            {{
+                {body}
+            }}"
+        }
+    }
+
+    fn render_struct_function(&self, base: &str, f: &ImplFn) -> String {
+        let name = f.name.to_lower_camel_case();
+        let params = render_params(base, &f.params);
+        let out_type = render_php_typename(base, f.params.output.as_ref());
+        let self_input = f.params.inputs.iter().find(|i| i.name == "self");
+        let static_keyword = match self_input.is_some() {
+            false => " static",
+            true => "",
+        };
+
+        let args = utils::join(f.params.inputs.iter(), ", ", |i| match &i.ty {
+            PolyType::Pointer(_) if i.name == "self" => "\\FFI::addr($self)".to_string(),
+            ty if i.name == "self" => convert_to_c(ty, "$this"),
+            ty => convert_to_c(ty, &format!("${}", i.name.to_lower_camel_case())),
+        });
+
+        // pointers to self may be modified in place
+        // so the data has to be copied back after the call
+        let call = render_call(base, &f.export_name, &args, &f.params);
+        let mut after = Vec::new();
+        if let Some(PolyType::Pointer(_)) = self_input.map(|i| &i.ty) {
+            after.push("$this->assign($self);".to_string());
+        }
+        after.extend(release_consumed(&f.params));
+
+        let body = then(call, after);
+        let body = match self_input.map(|i| &i.ty) {
+            Some(PolyType::Pointer(_)) => format!("$self = $this->toC();\n{body}"),
+            _ => body,
+        };
+        let body = indent_by(4, body);
+        let docs = render_docs(base, &f.docs, &f.params.inputs);
+
+        formatdoc! {"
+            {docs}public{static_keyword} function {name}({params}): {out_type}
+            {{
+                {body}
+            }}"
+        }
+    }
+}
+
+const RELEASED_GUARD: &str = indoc! {"
+    if ($this->ptr === 0) {
+        throw new \\LogicException('The handle was released or moved into a native call');
+    }"
+};
+
+fn loader_name(base: &str) -> String {
+    format!("\\{base}")
+}
+
+fn render_params(base: &str, params: &FnParams) -> String {
+    utils::join(
+        params.inputs.iter().filter(|i| i.name != "self"),
+        ", ",
+        |i| {
+            let ty = render_php_typename(base, Some(&i.ty));
+            format!("{ty} ${}", i.name.to_lower_camel_case())
+        },
+    )
+}
+
+fn render_call(base: &str, export_name: &str, args: &str, params: &FnParams) -> String {
+    let loader = loader_name(base);
+    let call = format!("{loader}::ffi()->{export_name}({args})");
+    match &params.output {
+        None => format!("{call};"),
+        Some(ty) => format!("return {};", convert_from_c(base, Some(ty), &call)),
+    }
+}
+
+/// Runs the statements after a call, keeping its result.
+fn then(call: String, after: Vec<String>) -> String {
+    if after.is_empty() {
+        return call;
+    }
+
+    let after = after.join("\n");
+    match call.strip_prefix("return ") {
+        None => format!("{call}\n{after}"),
+        Some(result) => format!("$result = {result}\n{after}\nreturn $result;"),
+    }
+}
+
+/// Releases the handles whose native value was moved into the call.
+fn release_consumed(params: &FnParams) -> Vec<String> {
+    let consumed = params.inputs.iter().filter(|i| i.consumed);
+    let consumed = consumed.map(|i| match i.name.as_str() {
+        "self" => "$this->release();".to_string(),
+        name => format!("${}->release();", name.to_lower_camel_case()),
+    });
+    consumed.collect()
+}
+
+fn convert_to_c(ty: &PolyType, value: &str) -> String {
+    match ty {
+        PolyType::Struct(_) => format!("{value}->toC()"),
+        _ => value.to_string(),
+    }
+}
+
+fn convert_from_c(base: &str, ty: Option<&PolyType>, value: &str) -> String {
+    match ty {
+        Some(PolyType::Struct(_)) => {
+            let class = render_php_typename(base, ty);
+            format!("{class}::fromC({value})")
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn collect_impls<'a>(m: &'a PolyMod, impls: &mut HashMap<String, &'a PolyImpl>) {
+    for (s, i) in m.structs() {
+        if let Some(i) = i {
+            impls.insert(render_c_structname(s), i);
+        }
+    }
+
+    for m in m.modules() {
+        collect_impls(m, impls);
+    }
+}
+
+fn render_interface(base: &str, s: &PolyStruct, methods: &[TraitFn]) -> String {
+    let name = render_php_classname(s);
+    let docs = render_docs(base, &s.docs, &[]);
+    let methods = indent_by(
+        4,
+        utils::join(methods.iter(), "\n", |f| {
+            let docs = render_docs(base, &f.docs, &f.params.inputs);
+            let params = render_params(base, &f.params);
+            let out_type = render_php_typename(base, f.params.output.as_ref());
+            let name = f.name.to_lower_camel_case();
+            format!("{docs}public function {name}({params}): {out_type};")
+        }),
+    );
+
+    formatdoc! {"
+        {docs}interface {name}Interface
+        {{
+            {methods}
+        }}
+
+        "
+    }
+}
+
+/// Renders the constructor that fills a vtable with closures calling an object.
+fn render_from_object(base: &str, s: &PolyStruct, methods: &[TraitFn]) -> String {
+    let name = render_php_classname(s);
+    let loader = loader_name(base);
+    let closures = indent_by(
+        4,
+        utils::join(methods.iter(), "\n", |f| {
+            let field = f.name.to_lower_camel_case();
+            let params = utils::join(f.params.inputs.iter(), "", |i| {
+                format!(", ${}", i.name.to_lower_camel_case())
+            });
+            let args = utils::join(f.params.inputs.iter(), ", ", |i| {
+                let value = format!("${}", i.name.to_lower_camel_case());
+                convert_from_c(base, Some(&i.ty), &value)
+            });
+            let call = format!("$target->{field}({args})");
+            let call = match &f.params.output {
+                None => call,
+                Some(ty) => convert_to_c(ty, &call),
+            };
+            format!("$self->{field} = fn($userData{params}) => {call};")
+        }),
+    );
+
+    // php keeps the closures alive, so the user data and destructor are not needed
+    formatdoc! {"
+        public static function fromObject({name}Interface $target): self
+        {{
+            $self = new self();
+            $self->userData = {loader}::ffi()->new('void*');
+            {closures}
+            $self->drop = fn($userData) => null;
+            return $self;
+        }}"
+    }
+}
+
+fn render_constant(base: &str, c: &PolyConst) -> String {
+    let name = c.name.to_shouty_snake_case();
+    let value = render_value(&c.ty, &c.value);
+    let docs = render_docs(base, &c.docs, &[]);
+    format!("{docs}const {name} = {value};")
+}
+
+/// Renders doc comments as a phpdoc block, followed by a new line if there are any.
+fn render_docs(base: &str, docs: &str, inputs: &[FnInput]) -> String {
+    let mut lines = docs.lines().map(ToString::to_string).collect::<Vec<_>>();
+    let inputs = inputs
+        .iter()
+        .filter(|i| i.name != "self" && !i.docs.is_empty());
+    for (index, i) in inputs.enumerate() {
+        if index == 0 && !lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        let ty = render_php_typename(base, Some(&i.ty));
+        let name = i.name.to_lower_camel_case();
+        lines.push(format!("@param {ty} ${name} {}", i.docs.replace('\n', " ")));
+    }
+
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    // a closing comment inside of the docs would end the block early
+    let lines = utils::join(lines.iter(), "\n", |l| match l.is_empty() {
+        true => " *".to_string(),
+        false => format!(" * {}", l.replace("*/", "*\\/")),
+    });
+    format!("/**\n{lines}\n */\n")
+}
+
+fn collect_constants<'a>(
+    m: &'a PolyMod,
+    namespace: String,
+    constants: &mut Vec<(String, &'a PolyConst)>,
+) {
+    for c in m.constants() {
+        constants.push((namespace.clone(), c));
+    }
+
+    for m in m.modules() {
+        let namespace = format!("{namespace}\\{}", m.name().to_pascal_case());
+        collect_constants(m, namespace, constants);
+    }
+}
+
+fn collect_functions<'a>(
+    m: &'a PolyMod,
+    namespace: String,
+    functions: &mut Vec<(String, &'a PolyFn)>,
+) {
+    for f in m.functions() {
+        functions.push((namespace.clone(), f));
+    }
+
+    for m in m.modules() {
+        let namespace = format!("{namespace}\\{}", m.name().to_pascal_case());
+        collect_functions(m, namespace, functions);
+    }
+}
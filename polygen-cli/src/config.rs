@@ -0,0 +1,107 @@
+use std::{fs, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
+
+use crate::CliError;
+
+/// The name of the config file that is read when no config is specified.
+pub const DEFAULT_CONFIG: &str = "polygen.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Lang {
+    Csharp,
+    Php,
+    Js,
+}
+
+impl Lang {
+    /// The languages reported by `ProblemKind::lang` for problems in these bindings.
+    pub fn problem_langs(self) -> &'static [&'static str] {
+        match self {
+            Self::Csharp => &["C#"],
+            // the php bindings declare the native functions in C
+            Self::Php => &["PHP", "C"],
+            Self::Js => &["javascript"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStyle {
+    DllImport,
+    LibraryImport,
+}
+
+impl From<ImportStyle> for polygen_csharp::ImportStyle {
+    fn from(value: ImportStyle) -> Self {
+        match value {
+            ImportStyle::DllImport => Self::DllImport,
+            ImportStyle::LibraryImport => Self::LibraryImport,
+        }
+    }
+}
+
+/// Options shared by every command.
+///
+/// Each option can also be set in the config file,
+/// the command line takes precedence over the config.
+#[derive(Debug, Default, Args, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    /// The json bag dump or compiled library to generate bindings for
+    #[arg(short, long)]
+    pub input: Option<PathBuf>,
+
+    /// The directory the bindings are written to
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+
+    /// The languages to generate bindings for
+    #[arg(short, long = "lang", value_enum)]
+    #[serde(default)]
+    pub langs: Vec<Lang>,
+
+    /// The name of the native library that is loaded by the bindings
+    #[arg(long)]
+    pub lib_name: Option<String>,
+
+    /// The namespace the bindings are placed in
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// The kind of import used by the csharp bindings
+    #[arg(long, value_enum)]
+    pub import_style: Option<ImportStyle>,
+}
+
+impl Options {
+    /// Loads the config file and fills in every option that was not set on the command line.
+    ///
+    /// The default config is only loaded if it exists.
+    pub fn resolve(self, config: Option<PathBuf>) -> Result<Self, CliError> {
+        let path = match config {
+            Some(path) => path,
+            None => match PathBuf::from(DEFAULT_CONFIG) {
+                path if path.exists() => path,
+                _ => return Ok(self),
+            },
+        };
+
+        let source = fs::read_to_string(&path).map_err(|e| CliError::Io(path.clone(), e))?;
+        let config: Options = toml::from_str(&source).map_err(|e| CliError::Config(path, e))?;
+        Ok(Self {
+            input: self.input.or(config.input),
+            out: self.out.or(config.out),
+            langs: match self.langs.is_empty() {
+                true => config.langs,
+                false => self.langs,
+            },
+            lib_name: self.lib_name.or(config.lib_name),
+            namespace: self.namespace.or(config.namespace),
+            import_style: self.import_style.or(config.import_style),
+        })
+    }
+}
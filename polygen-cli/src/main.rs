@@ -0,0 +1,169 @@
+mod config;
+mod render;
+
+use std::{fs, io, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use polygen::{
+    diff::{BagDiff, Severity, UnexpectedChanges},
+    ir::IrError,
+    validate::Problem,
+};
+use polygen_csharp::CSharpError;
+use polygen_js::JsError;
+use polygen_php::PhpError;
+use thiserror::Error;
+
+use config::Options;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "polygen",
+    version,
+    about = "Generates bindings for libraries built with polygen"
+)]
+struct Cli {
+    /// The config file to read options from [default: polygen.toml]
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generates the bindings and writes them to the output directory
+    Gen(Options),
+    /// Fails if the bindings in the output directory are out of date
+    Check(Options),
+    /// Compares two bags and fails on changes that are more severe than allowed
+    Diff(DiffOptions),
+}
+
+#[derive(Debug, Args)]
+struct DiffOptions {
+    /// The json bag dump or compiled library of the previous release
+    old: PathBuf,
+
+    /// The json bag dump or compiled library to compare against
+    new: PathBuf,
+
+    /// The most severe kind of change that is allowed
+    #[arg(short, long, value_enum, default_value = "compatible")]
+    allow: Allow,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Allow {
+    Compatible,
+    SourceBreaking,
+    AbiBreaking,
+}
+
+impl From<Allow> for Severity {
+    fn from(value: Allow) -> Self {
+        match value {
+            Allow::Compatible => Self::Compatible,
+            Allow::SourceBreaking => Self::SourceBreaking,
+            Allow::AbiBreaking => Self::AbiBreaking,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("Failed to access `{}`: {1}", .0.display())]
+    Io(PathBuf, io::Error),
+    #[error("Invalid config `{}`: {1}", .0.display())]
+    Config(PathBuf, toml::de::Error),
+    #[error("Invalid bag `{}`: {1}", .0.display())]
+    Bag(PathBuf, IrError),
+    #[error("Invalid bag `{}`:\n{}", .0.display(), display_problems(.1))]
+    Invalid(PathBuf, Vec<Problem>),
+    #[error("Failed to load the library `{}`: {1}", .0.display())]
+    Library(PathBuf, libloading::Error),
+    #[error("The library `{}` does not embed a bag, call `polygen::export_bag!` once in the library", .0.display())]
+    MissingBag(PathBuf),
+    #[error("The bag embedded in `{}` is not valid utf8", .0.display())]
+    InvalidUtf8(PathBuf),
+    #[error("Missing option `{0}`, pass it on the command line or set it in the config")]
+    MissingOption(&'static str),
+    #[error("The bindings are out of date: {}", display_paths(.0))]
+    Stale(Vec<PathBuf>),
+    #[error(transparent)]
+    Diff(#[from] UnexpectedChanges),
+    #[error("Failed to render the C# bindings: {0}")]
+    Csharp(#[from] CSharpError),
+    #[error("Failed to render the PHP bindings: {0}")]
+    Php(#[from] PhpError),
+    #[error("Failed to render the javascript bindings: {0}")]
+    Js(#[from] JsError),
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    let paths = paths.iter().map(|p| format!("`{}`", p.display()));
+    paths.collect::<Vec<_>>().join(", ")
+}
+
+fn display_problems(problems: &[Problem]) -> String {
+    let problems = problems.iter().map(|p| format!("  {p}"));
+    problems.collect::<Vec<_>>().join("\n")
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    match cli.command {
+        Command::Gen(options) => {
+            let options = options.resolve(cli.config)?;
+            for output in render::render(&options)? {
+                if let Some(parent) = output.path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| CliError::Io(parent.into(), e))?;
+                }
+
+                fs::write(&output.path, output.contents)
+                    .map_err(|e| CliError::Io(output.path.clone(), e))?;
+                println!("wrote `{}`", output.path.display());
+            }
+        }
+        Command::Check(options) => {
+            let options = options.resolve(cli.config)?;
+            let stale = render::render(&options)?
+                .into_iter()
+                .filter(|output| match fs::read_to_string(&output.path) {
+                    Ok(contents) => contents != output.contents,
+                    Err(_) => true,
+                })
+                .map(|output| output.path)
+                .collect::<Vec<_>>();
+
+            if !stale.is_empty() {
+                return Err(CliError::Stale(stale));
+            }
+
+            println!("the bindings are up to date");
+        }
+        Command::Diff(options) => {
+            let old = render::load_bag(&options.old)?;
+            let new = render::load_bag(&options.new)?;
+            let diff = BagDiff::new(&old, &new);
+            for change in &diff.changes {
+                println!("{change}");
+            }
+
+            diff.check(options.allow.into())?;
+            println!("no unexpected changes");
+        }
+    }
+
+    Ok(())
+}
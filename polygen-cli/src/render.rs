@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libloading::Library;
+use polygen::PolyBag;
+use polygen_csharp::CSharpRenderer;
+use polygen_js::JsRenderer;
+use polygen_php::PhpRenderer;
+
+use crate::{
+    config::{Lang, Options},
+    CliError,
+};
+
+/// A rendered binding file.
+pub struct Output {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+pub fn render(options: &Options) -> Result<Vec<Output>, CliError> {
+    let input = options
+        .input
+        .as_ref()
+        .ok_or(CliError::MissingOption("input"))?;
+    let out = options.out.as_ref().ok_or(CliError::MissingOption("out"))?;
+    let namespace = options
+        .namespace
+        .as_ref()
+        .ok_or(CliError::MissingOption("namespace"))?;
+    if options.langs.is_empty() {
+        return Err(CliError::MissingOption("lang"));
+    }
+
+    // problems of other languages do not affect the selected bindings
+    let bag = load_bag(input)?;
+    let problems = bag.validate().into_iter().filter(|p| match p.kind.lang() {
+        None => true,
+        Some(lang) => options
+            .langs
+            .iter()
+            .any(|l| l.problem_langs().contains(&lang)),
+    });
+    let problems = problems.collect::<Vec<_>>();
+    if !problems.is_empty() {
+        return Err(CliError::Invalid(input.clone(), problems));
+    }
+
+    let mut outputs = Vec::new();
+    for lang in &options.langs {
+        let (extension, contents) = match lang {
+            Lang::Csharp => {
+                let renderer = CSharpRenderer {
+                    lib_name: lib_name(options)?,
+                    namespace: namespace.clone(),
+                    import_style: options.import_style.map(Into::into).unwrap_or_default(),
+                };
+                ("cs", renderer.render(&bag)?)
+            }
+            Lang::Php => {
+                let renderer = PhpRenderer {
+                    lib_name: lib_name(options)?,
+                    namespace: namespace.clone(),
+                };
+                ("php", renderer.render(&bag)?)
+            }
+            Lang::Js => ("mjs", JsRenderer.render(&bag)?),
+        };
+
+        outputs.push(Output {
+            path: out.join(format!("{namespace}.{extension}")),
+            contents,
+        });
+    }
+
+    Ok(outputs)
+}
+
+pub fn load_bag(input: &PathBuf) -> Result<PolyBag, CliError> {
+    let json = match input.extension().and_then(|e| e.to_str()) {
+        Some("so" | "dylib" | "dll") => load_embedded_json(input)?,
+        _ => fs::read_to_string(input).map_err(|e| CliError::Io(input.clone(), e))?,
+    };
+
+    PolyBag::from_json(&json).map_err(|e| CliError::Bag(input.clone(), e))
+}
+
+/// Reads the bag embedded in a library using `polygen::export_bag!`.
+fn load_embedded_json(input: &PathBuf) -> Result<String, CliError> {
+    type BagJson = unsafe extern "C" fn(*mut usize) -> *const u8;
+
+    // SAFETY: loading the library runs its initializers, which is required to fill the registry
+    unsafe {
+        // bare file names would be looked up in the system library paths instead
+        let path = Path::new(".").join(input);
+        let library = Library::new(path).map_err(|e| CliError::Library(input.clone(), e))?;
+        let bag_json = library
+            .get::<BagJson>(b"__polygen_bag_json")
+            .map_err(|_| CliError::MissingBag(input.clone()))?;
+
+        // copy the json out before the library is unloaded
+        let mut len = 0;
+        let ptr = bag_json(&mut len);
+        let bytes = std::slice::from_raw_parts(ptr, len).to_vec();
+        String::from_utf8(bytes).map_err(|_| CliError::InvalidUtf8(input.clone()))
+    }
+}
+
+fn lib_name(options: &Options) -> Result<String, CliError> {
+    options
+        .lib_name
+        .clone()
+        .ok_or(CliError::MissingOption("lib-name"))
+}
@@ -0,0 +1,215 @@
+use std::{
+    env::{
+        self,
+        consts::{DLL_PREFIX, DLL_SUFFIX},
+    },
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use polygen::{ir::SCHEMA_VERSION, PolyBag};
+
+static FIXTURE: &str = include_str!("fixtures/bag.json");
+
+/// Creates an empty directory for a test, so no `polygen.toml` is picked up.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn polygen(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_polygen"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+const GEN_ARGS: &[&str] = &[
+    "--input",
+    "bag.json",
+    "--out",
+    "bindings",
+    "--lib-name",
+    "simple_lib",
+    "--namespace",
+    "SimpleLib",
+];
+
+#[test]
+fn gen_and_check() {
+    let dir = test_dir("gen_and_check");
+    fs::write(dir.join("bag.json"), FIXTURE).unwrap();
+
+    let args = [
+        &["gen"],
+        GEN_ARGS,
+        &["-l", "csharp", "-l", "php", "-l", "js"],
+    ]
+    .concat();
+    let output = polygen(&dir, &args);
+    assert!(output.status.success(), "{}", stderr(&output));
+    for file in ["SimpleLib.cs", "SimpleLib.php", "SimpleLib.mjs"] {
+        assert!(dir.join("bindings").join(file).exists());
+    }
+
+    let args = [
+        &["check"],
+        GEN_ARGS,
+        &["-l", "csharp", "-l", "php", "-l", "js"],
+    ]
+    .concat();
+    assert!(polygen(&dir, &args).status.success());
+
+    // changing the bindings by hand makes them stale
+    fs::write(dir.join("bindings/SimpleLib.php"), "<?php").unwrap();
+    let output = polygen(&dir, &args);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("The bindings are out of date: `bindings/SimpleLib.php`"));
+}
+
+#[test]
+fn config_options() {
+    let dir = test_dir("config_options");
+    fs::write(dir.join("bag.json"), FIXTURE).unwrap();
+    let config =
+        "input = \"bag.json\"\nout = \"bindings\"\nlangs = [\"js\"]\nnamespace = \"SimpleLib\"\n";
+    fs::write(dir.join("polygen.toml"), config).unwrap();
+
+    let output = polygen(&dir, &["gen"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(dir.join("bindings/SimpleLib.mjs").exists());
+}
+
+#[test]
+fn missing_options() {
+    let dir = test_dir("missing_options");
+    fs::write(dir.join("bag.json"), FIXTURE).unwrap();
+
+    let output = polygen(&dir, &["gen", "--input", "bag.json", "--out", "bindings"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Missing option `namespace`"));
+
+    // only csharp and php load the library by name
+    let args = [
+        "gen",
+        "-i",
+        "bag.json",
+        "-o",
+        "bindings",
+        "--namespace",
+        "SimpleLib",
+    ];
+    let output = polygen(&dir, &[&args[..], &["-l", "php"]].concat());
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Missing option `lib-name`"));
+    assert!(polygen(&dir, &[&args[..], &["-l", "js"]].concat())
+        .status
+        .success());
+
+    let output = polygen(&dir, &args);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Missing option `lang`"));
+}
+
+#[test]
+fn version_mismatch() {
+    let dir = test_dir("version_mismatch");
+    let current = format!("\"version\": {SCHEMA_VERSION}");
+    assert!(FIXTURE.contains(&current));
+    fs::write(
+        dir.join("bag.json"),
+        FIXTURE.replace(&current, "\"version\": 0"),
+    )
+    .unwrap();
+
+    let args = [&["gen"], GEN_ARGS, &["-l", "js"]].concat();
+    let output = polygen(&dir, &args);
+    assert!(!output.status.success());
+    let expected = format!("Unsupported schema version 0, expected version {SCHEMA_VERSION}");
+    assert!(stderr(&output).contains(&expected), "{}", stderr(&output));
+}
+
+#[test]
+fn problems_of_other_languages() {
+    // `object` is only reserved in C#
+    let dir = test_dir("problems_of_other_languages");
+    let json = FIXTURE.replace("\"name\": \"item\"", "\"name\": \"object\"");
+    fs::write(dir.join("bag.json"), json).unwrap();
+
+    let output = polygen(&dir, &[&["gen"], GEN_ARGS, &["-l", "csharp"]].concat());
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("which is a reserved keyword in C#"));
+
+    let output = polygen(
+        &dir,
+        &[&["gen"], GEN_ARGS, &["-l", "js", "-l", "php"]].concat(),
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn library_without_bag() {
+    let dir = test_dir("library_without_bag");
+    fs::write(
+        dir.join("lib.rs"),
+        "#[no_mangle]\npub extern \"C\" fn unrelated() {}\n",
+    )
+    .unwrap();
+
+    let library = format!("{DLL_PREFIX}plain{DLL_SUFFIX}");
+    let status = Command::new(env::var("RUSTC").unwrap_or("rustc".to_string()))
+        .current_dir(&dir)
+        .args(["--crate-type", "cdylib", "-o", &library, "lib.rs"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let args = [&["gen", "--input", &library], &GEN_ARGS[2..], &["-l", "js"]].concat();
+    let output = polygen(&dir, &args);
+    assert!(!output.status.success());
+    let expected = format!(
+        "The library `{library}` does not embed a bag, call `polygen::export_bag!` once in the library"
+    );
+    assert!(stderr(&output).contains(&expected), "{}", stderr(&output));
+
+    let args = [
+        &["gen", "--input", "missing.so"],
+        &GEN_ARGS[2..],
+        &["-l", "js"],
+    ]
+    .concat();
+    let output = polygen(&dir, &args);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Failed to load the library `missing.so`"));
+}
+
+#[test]
+fn diff() {
+    let dir = test_dir("diff");
+    fs::write(dir.join("bag.json"), FIXTURE).unwrap();
+    let empty = PolyBag::new("Native").to_json().unwrap();
+    fs::write(dir.join("empty.json"), empty).unwrap();
+
+    let output = polygen(&dir, &["diff", "bag.json", "bag.json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no unexpected changes"));
+
+    // adding functions is compatible, removing them breaks the bindings
+    assert!(polygen(&dir, &["diff", "empty.json", "bag.json"])
+        .status
+        .success());
+    let output = polygen(&dir, &["diff", "bag.json", "empty.json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("create_boxed"));
+
+    let args = ["diff", "bag.json", "empty.json", "--allow", "abi-breaking"];
+    assert!(polygen(&dir, &args).status.success());
+}
@@ -6,13 +6,16 @@ mod process;
 #[proc_macro_attribute]
 pub fn polygen(attr: TokenStream, item: TokenStream) -> TokenStream {
     let attr = syn::parse_macro_input!(attr as process::PolyAttr);
-    let item = syn::parse_macro_input!(item as syn::Item);
+    let mut item = syn::parse_macro_input!(item as syn::Item);
 
     use syn::Item as I;
-    let processed = match &item {
+    let processed = match &mut item {
+        I::Const(item) => process::polyconst(&attr, item),
         I::Struct(item) => process::polystruct(&attr, item),
         I::Fn(item) => process::polyfn(&attr, item),
         I::Impl(item) => process::polyimpl(&attr, item),
+        I::Static(item) => process::polystatic(&attr, item),
+        I::Trait(item) => process::polytrait(&attr, item),
         _ => quote!(compile_error!("This item is unsupported by polygen");),
     };
 
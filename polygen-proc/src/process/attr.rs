@@ -1,14 +1,15 @@
 use std::ops::Deref;
 
 use proc_macro2::Ident;
+use quote::quote_spanned;
 use syn::{parse::Parse, punctuated::Punctuated, Token};
 
 pub struct PolyAttr {
-    items: Vec<String>,
+    items: Vec<Ident>,
 }
 
 impl Deref for PolyAttr {
-    type Target = Vec<String>;
+    type Target = Vec<Ident>;
 
     fn deref(&self) -> &Self::Target {
         &self.items
@@ -18,11 +19,91 @@ impl Deref for PolyAttr {
 impl Parse for PolyAttr {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let idents = Punctuated::<Ident, Token![,]>::parse_terminated(input)?;
-        let mut items = Vec::with_capacity(idents.len());
-        for ident in idents.into_iter() {
-            items.push(ident.to_string());
+        Ok(Self {
+            items: idents.into_iter().collect(),
+        })
+    }
+}
+
+impl PolyAttr {
+    /// Removes all `#[polygen(...)]` helper attributes from `attrs` and merges them.
+    pub fn take(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Self> {
+        let mut items = Vec::new();
+        let mut error = None;
+        attrs.retain(|attr| {
+            if !attr.path().is_ident("polygen") {
+                return true;
+            }
+
+            if let syn::Meta::List(_) = attr.meta {
+                match attr.parse_args::<PolyAttr>() {
+                    Ok(attr) => items.extend(attr.items),
+                    Err(e) => error = Some(e),
+                }
+            }
+
+            false
+        });
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(Self { items }),
         }
+    }
 
-        Ok(Self { items })
+    /// Returns true if the option `name` was provided.
+    pub fn has(&self, name: &str) -> bool {
+        self.items.iter().any(|item| item == name)
     }
+
+    /// Returns a compile error for the first option that is not in `allowed`.
+    pub fn check(&self, allowed: &[&str]) -> Option<proc_macro2::TokenStream> {
+        let item = self
+            .items
+            .iter()
+            .find(|item| !allowed.iter().any(|a| *item == a))?;
+
+        let message = match allowed.len() {
+            0 => format!("`{item}` is not a valid option here. No options are supported"),
+            _ => format!(
+                "`{item}` is not a valid option here. Expected one of: {}",
+                allowed.join(", ")
+            ),
+        };
+
+        Some(quote_spanned! { item.span() =>
+            compile_error!(#message);
+        })
+    }
+}
+
+/// Collects the doc comments in `attrs` into a single string with one line per comment.
+pub fn docs(attrs: &[syn::Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => Some(s.value()),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    // doc comments keep the space after `///`
+    let lines = lines.flat_map(|doc| {
+        let lines = doc
+            .split('\n')
+            .map(|l| l.strip_prefix(' ').unwrap_or(l).to_string());
+        lines.collect::<Vec<_>>()
+    });
+
+    lines.collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// Removes and returns the doc comments on a function parameter, which rustc does not allow.
+pub fn take_docs(attrs: &mut Vec<syn::Attribute>) -> String {
+    let docs = docs(attrs);
+    attrs.retain(|attr| !attr.path().is_ident("doc"));
+    docs
 }
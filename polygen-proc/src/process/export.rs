@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use quote::{quote, ToTokens};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Creates the name of an exported function from a hash of its crate, source file and signature.
+///
+/// Unlike a random id the name stays the same between builds, so it only changes with the item.
+/// The source file stands in for the module path, which is not available in proc macros.
+pub fn export_ident(prefix: &str, ident: &syn::Ident, signature: impl ToTokens) -> syn::Ident {
+    let krate = std::env::var("CARGO_CRATE_NAME").unwrap_or_default();
+
+    // absolute paths differ between machines, so the file is made relative to the crate
+    let file = proc_macro::Span::call_site().local_file();
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let file = match &file {
+        Some(file) => file.strip_prefix(&manifest_dir).unwrap_or(file),
+        None => Path::new(""),
+    };
+
+    let input = format!(
+        "{krate}\n{}\n{ident}\n{}",
+        file.display(),
+        signature.to_token_stream()
+    );
+    let mut hash = FNV_OFFSET;
+    for byte in input.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    syn::Ident::new(&format!("{prefix}_{ident}_{hash:016x}"), ident.span())
+}
+
+/// Returns the types of the inputs and the output of a function, without names or attributes.
+pub fn signature_types(sig: &syn::Signature) -> proc_macro2::TokenStream {
+    let inputs = sig.inputs.iter().map(|input| match input {
+        syn::FnArg::Receiver(receiver) => receiver.ty.to_token_stream(),
+        syn::FnArg::Typed(typed) => typed.ty.to_token_stream(),
+    });
+    let output = &sig.output;
+    quote!( (#(#inputs),*) #output )
+}
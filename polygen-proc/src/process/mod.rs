@@ -1,9 +1,17 @@
 mod attr;
+mod export;
+mod polyconst;
 mod polyfn;
 mod polyimpl;
+mod polystatic;
 mod polystruct;
+mod polytrait;
 
 pub use attr::*;
+pub use export::*;
+pub use polyconst::*;
 pub use polyfn::*;
 pub use polyimpl::*;
+pub use polystatic::*;
 pub use polystruct::*;
+pub use polytrait::*;
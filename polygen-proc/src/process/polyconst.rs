@@ -0,0 +1,55 @@
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use super::{docs, PolyAttr};
+
+pub fn polyconst(attrs: &PolyAttr, item: &syn::ItemConst) -> proc_macro2::TokenStream {
+    if let Some(error) = attrs.check(&["manual"]) {
+        return error;
+    }
+
+    if !item.generics.params.empty_or_trailing() {
+        return quote_spanned! { item.generics.params.span() =>
+            compile_error!("Generics are not supported by #[polygen] attribute");
+        };
+    }
+
+    let ident = &item.ident;
+    let ty = &item.ty;
+    let docs = docs(&item.attrs);
+
+    // submit the constant to the crate registry
+    let manual = attrs.has("manual");
+    let register = quote! {
+        ::polygen::__private::inventory::submit! {
+            ::polygen::__private::PolyRegistration {
+                module: module_path!(),
+                line: line!(),
+                manual: #manual,
+                register: ::polygen::PolyBag::register_const::<#ident>,
+            }
+        }
+    };
+
+    // the value is read through a function, since converting it is not possible in a const
+    let value = quote_spanned! { ty.span() =>
+        || <#ty as ::polygen::__private::ExportedPolyValue>::poly_value(#ident)
+    };
+
+    quote! {
+        #register
+
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        pub struct #ident {}
+        unsafe impl ::polygen::__private::ExportedPolyConst for #ident {
+            const CONST: ::polygen::items::PolyConst = ::polygen::items::PolyConst {
+                module: module_path!(),
+                name: stringify!(#ident),
+                docs: #docs,
+                ty: <#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                value: #value,
+            };
+        }
+    }
+}
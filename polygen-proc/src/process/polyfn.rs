@@ -1,29 +1,33 @@
 use quote::{quote, quote_spanned, ToTokens};
-use rand::distributions::{Alphanumeric, DistString};
 use syn::{punctuated::Punctuated, spanned::Spanned, Token};
 
-use super::PolyAttr;
+use super::{docs, export_ident, signature_types, take_docs, PolyAttr};
+
+pub fn polyfn(attrs: &PolyAttr, item: &mut syn::ItemFn) -> proc_macro2::TokenStream {
+    if let Some(error) = attrs.check(&["trivial", "manual"]) {
+        return error;
+    }
 
-pub fn polyfn(_attrs: &PolyAttr, item: &syn::ItemFn) -> proc_macro2::TokenStream {
     if !item.sig.generics.params.empty_or_trailing() {
         return quote_spanned! { item.sig.generics.params.span() =>
             compile_error!("Generics are not supported by #[polygen] attribute");
         };
     }
 
-    // generate random id to prevent exported name collisions
-    // this can be replaced with the module name once available in proc macros
-    let rand_id: String = Alphanumeric.sample_string(&mut rand::thread_rng(), 6);
-
     let ident = &item.sig.ident;
-    let export_ident = syn::Ident::new(&format!("__polygen_fn_{ident}_{rand_id}"), ident.span());
+    let docs = docs(&item.attrs);
+    let trivial = attrs.has("trivial");
+    let export_ident = export_ident("__polygen_fn", ident, signature_types(&item.sig));
     let mut into_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
     let mut fn_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
     let mut fn_inputs = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
-    for input in &item.sig.inputs {
+    let mut wasm_into_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+    let mut wasm_fn_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+    for input in &mut item.sig.inputs {
         use syn::FnArg as A;
         match input {
             A::Typed(typed) => {
+                let input_docs = take_docs(&mut typed.attrs);
                 let ty = &typed.ty;
                 let pat_ident = match &*typed.pat {
                     syn::Pat::Ident(ident) => &ident.ident,
@@ -44,10 +48,18 @@ pub fn polyfn(_attrs: &PolyAttr, item: &syn::ItemFn) -> proc_macro2::TokenStream
                 fn_args.push(quote_spanned! { ty.span() =>
                     #pat_ident: <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
                 });
+                wasm_into_args.push(quote_spanned! { ty.span() =>
+                    ::core::ptr::read(#pat_ident).into()
+                });
+                wasm_fn_args.push(quote_spanned! { ty.span() =>
+                    #pat_ident: *const <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+                });
                 fn_inputs.push(quote_spanned! { ty.span() =>
                     ::polygen::items::FnInput {
                         name: stringify!(#pat_ident),
+                        docs: #input_docs,
                         ty: &<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                        consumed: <#ty as ::polygen::__private::ExportedPolyStruct>::CONSUMES_HANDLE,
                     }
                 });
             }
@@ -67,7 +79,36 @@ pub fn polyfn(_attrs: &PolyAttr, item: &syn::ItemFn) -> proc_macro2::TokenStream
         ),
     };
 
+    // wasm exports take every argument by pointer and write the output through a pointer
+    // this keeps the exports independent from how the wasm C abi passes aggregates
+    let wasm_call = quote!( #ident( #wasm_into_args ) );
+    let wasm_body = match &item.sig.output {
+        syn::ReturnType::Default => quote!( #wasm_call; ),
+        syn::ReturnType::Type(_, ty) => {
+            wasm_fn_args.insert(0, quote_spanned! { ty.span() =>
+                __polygen_out: *mut <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+            });
+            quote!( __polygen_out.write(#wasm_call.into()); )
+        }
+    };
+
+    // submit the function to the crate registry
+    // manual items are only used to verify the abi and not registered automatically
+    let manual = attrs.has("manual");
+    let register = quote! {
+        ::polygen::__private::inventory::submit! {
+            ::polygen::__private::PolyRegistration {
+                module: module_path!(),
+                line: line!(),
+                manual: #manual,
+                register: ::polygen::PolyBag::register_function::<#ident>,
+            }
+        }
+    };
+
     return quote! {
+        #register
+
         #[doc(hidden)]
         #[allow(non_camel_case_types)]
         pub struct #ident {}
@@ -75,7 +116,9 @@ pub fn polyfn(_attrs: &PolyAttr, item: &syn::ItemFn) -> proc_macro2::TokenStream
             const FUNCTION: ::polygen::items::PolyFn = ::polygen::items::PolyFn {
                 module: module_path!(),
                 name: stringify!(#ident),
+                docs: #docs,
                 export_name: stringify!(#export_ident),
+                trivial: #trivial,
                 params: ::polygen::items::FnParams {
                     inputs: &[#fn_inputs],
                     output: #polyout,
@@ -87,8 +130,18 @@ pub fn polyfn(_attrs: &PolyAttr, item: &syn::ItemFn) -> proc_macro2::TokenStream
         #[doc(hidden)]
         #[allow(non_snake_case)]
         #[allow(improper_ctypes_definitions)]
+        #[cfg(not(target_arch = "wasm32"))]
         extern "C" fn #export_ident( #fn_args ) #output {
             #ident( #into_args ).into()
         }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #[allow(unused_unsafe)]
+        #[cfg(target_arch = "wasm32")]
+        extern "C" fn #export_ident( #wasm_fn_args ) {
+            unsafe { #wasm_body }
+        }
     };
 }
@@ -1,10 +1,13 @@
 use quote::{quote, quote_spanned, ToTokens, TokenStreamExt};
-use rand::distributions::{Alphanumeric, DistString};
 use syn::{punctuated::Punctuated, spanned::Spanned, Token};
 
-use super::PolyAttr;
+use super::{docs, export_ident, signature_types, take_docs, PolyAttr};
+
+pub fn polyimpl(attrs: &PolyAttr, item: &mut syn::ItemImpl) -> proc_macro2::TokenStream {
+    if let Some(error) = attrs.check(&["manual"]) {
+        return error;
+    }
 
-pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenStream {
     // fail on generics
     if !item.generics.params.empty_or_trailing() {
         return quote_spanned! { item.generics.params.span() =>
@@ -12,18 +15,46 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
         };
     }
 
+    // methods of trait impls are neither public nor callable without the trait in scope
+    if let Some((_, path, _)) = &item.trait_ {
+        return syn::Error::new_spanned(
+            path,
+            "Trait impl blocks are not supported by #[polygen]. \
+            Export the trait with #[polygen] or move the methods into an inherent impl block",
+        )
+        .to_compile_error();
+    }
+
     // get the self type for use later
     let self_ty = &item.self_ty;
 
-    // generate random id to prevent exported name collisions
-    // this can be replaced with the module name once available in proc macros
-    let rand_id: String = Alphanumeric.sample_string(&mut rand::thread_rng(), 6);
+    // methods taking a reference to self are called through a pointer to the receiver
+    let receiver_impl = quote! {
+        <<#self_ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+            as ::polygen::__private::PolyReceiver<#self_ty>>
+    };
 
     let mut exports = quote!();
     let mut polyfns = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
-    for implitem in &item.items {
+    let mut polyconsts = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+    for implitem in &mut item.items {
         match implitem {
             syn::ImplItem::Fn(implfn) => {
+                // collect and remove the helper attributes on the function
+                let fn_attrs = match PolyAttr::take(&mut implfn.attrs) {
+                    Ok(fn_attrs) => fn_attrs,
+                    Err(e) => return e.to_compile_error(),
+                };
+                if let Some(error) = fn_attrs.check(&["trivial", "skip", "include"]) {
+                    return error;
+                }
+
+                match is_exported(&implfn.vis, &fn_attrs) {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(e) => return e.to_compile_error(),
+                }
+
                 // fail on generics
                 if !implfn.sig.generics.params.empty_or_trailing() {
                     return quote_spanned! { item.generics.params.span() =>
@@ -35,23 +66,37 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
                 let mut into_params = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
                 let mut export_params = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
                 let mut polyfn_input = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
-                for input in &implfn.sig.inputs {
+                let mut wasm_into_params = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+                let mut wasm_export_params =
+                    Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+                let fn_docs = docs(&implfn.attrs);
+                for input in &mut implfn.sig.inputs {
                     match input {
                         syn::FnArg::Receiver(rec) => match rec.reference {
                             Some(_) => {
                                 export_params.push(quote_spanned! { rec.self_token.span() =>
-                                    __polygen_self_ptr: *mut #self_ty
+                                    __polygen_self_ptr: *mut #receiver_impl::Receiver
                                 });
                                 variables.append_all(quote_spanned! { rec.self_token.span() =>
-                                    let __polygen_self_ref = unsafe { &mut *__polygen_self_ptr };
+                                    let __polygen_self_ref = unsafe {
+                                        #receiver_impl::receiver(&mut *__polygen_self_ptr)
+                                    };
                                 });
                                 into_params.push(quote_spanned! { rec.self_token.span() =>
                                     __polygen_self_ref
                                 });
+                                wasm_export_params.push(quote_spanned! { rec.self_token.span() =>
+                                    __polygen_self_ptr: *mut #receiver_impl::Receiver
+                                });
+                                wasm_into_params.push(quote_spanned! { rec.self_token.span() =>
+                                    #receiver_impl::receiver(&mut *__polygen_self_ptr)
+                                });
                                 polyfn_input.push(quote_spanned! { rec.self_token.span() =>
                                     ::polygen::items::FnInput {
                                         name: "self",
-                                        ty: &<*mut #self_ty as ::polygen::__private::ExportedPolyStruct>::STRUCT
+                                        docs: "",
+                                        ty: &<*mut #receiver_impl::Receiver as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                                        consumed: false,
                                     }
                                 });
                             }
@@ -62,15 +107,24 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
                                 into_params.push(quote_spanned! { rec.self_token.span() =>
                                     __polygen_self.into()
                                 });
+                                wasm_export_params.push(quote_spanned! { rec.self_token.span() =>
+                                    __polygen_self: *const <#self_ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+                                });
+                                wasm_into_params.push(quote_spanned! { rec.self_token.span() =>
+                                    ::core::ptr::read(__polygen_self).into()
+                                });
                                 polyfn_input.push(quote_spanned! { rec.self_token.span() =>
                                     ::polygen::items::FnInput {
                                         name: "self",
-                                        ty: &<#self_ty as ::polygen::__private::ExportedPolyStruct>::STRUCT
+                                        docs: "",
+                                        ty: &<#self_ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                                        consumed: <#self_ty as ::polygen::__private::ExportedPolyStruct>::CONSUMES_HANDLE,
                                     }
                                 });
                             }
                         },
                         syn::FnArg::Typed(typed) => {
+                            let input_docs = take_docs(&mut typed.attrs);
                             let ty = &typed.ty;
                             let pat_ident = match &*typed.pat {
                                 syn::Pat::Ident(ident) => &ident.ident,
@@ -91,24 +145,44 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
                                 #pat_ident: <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
                             });
                             into_params.push(quote_spanned!( ty.span() => #pat_ident.into() ));
+                            wasm_export_params.push(quote_spanned! { ty.span() =>
+                                #pat_ident: *const <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+                            });
+                            wasm_into_params.push(quote_spanned! { ty.span() =>
+                                ::core::ptr::read(#pat_ident).into()
+                            });
                             polyfn_input.push(quote_spanned! { ty.span() =>
                                 ::polygen::items::FnInput {
                                     name: stringify!(#pat_ident),
+                                    docs: #input_docs,
                                     ty: &<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                                    consumed: <#ty as ::polygen::__private::ExportedPolyStruct>::CONSUMES_HANDLE,
                                 }
                             });
                         }
                     }
                 }
 
-                let (output, polyout) = match &implfn.sig.output {
-                    syn::ReturnType::Default => (proc_macro2::TokenStream::new(), quote!(None)),
+                let ident = &implfn.sig.ident;
+                let trivial = fn_attrs.has("trivial");
+                let wasm_call = quote!( #self_ty::#ident( #wasm_into_params ) );
+                let (output, polyout, wasm_body) = match &implfn.sig.output {
+                    syn::ReturnType::Default => (
+                        proc_macro2::TokenStream::new(),
+                        quote!(None),
+                        quote!( #wasm_call; ),
+                    ),
                     syn::ReturnType::Type(_, ty) => {
                         let mut ty = ty;
                         if ty.to_token_stream().to_string() == "Self" {
                             ty = self_ty;
                         }
 
+                        // wasm exports write their output through a pointer
+                        wasm_export_params.insert(0, quote_spanned! { ty.span() =>
+                            __polygen_out: *mut <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+                        });
+
                         (
                             quote_spanned! { ty.span() =>
                                 -> <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
@@ -116,17 +190,20 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
                             quote_spanned! { ty.span() =>
                                 Some(<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT)
                             },
+                            quote!( __polygen_out.write(#wasm_call.into()); ),
                         )
                     }
                 };
 
-                let ident = &implfn.sig.ident;
+                let signature = signature_types(&implfn.sig);
                 let export_ident =
-                    syn::Ident::new(&format!("__polygen_implfn_{ident}_{rand_id}"), ident.span());
+                    export_ident("__polygen_implfn", ident, quote!( #self_ty #signature ));
                 polyfns.push(quote! {
                     ::polygen::items::ImplFn {
                         name: stringify!(#ident),
+                        docs: #fn_docs,
                         export_name: stringify!(#export_ident),
+                        trivial: #trivial,
                         params: ::polygen::items::FnParams {
                             inputs: &[#polyfn_input],
                             output: #polyout,
@@ -139,12 +216,68 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
                     #[doc(hidden)]
                     #[allow(non_snake_case)]
                     #[allow(improper_ctypes_definitions)]
+                    #[cfg(not(target_arch = "wasm32"))]
                     extern "C" fn #export_ident( #export_params ) #output {
                         #variables
                         #self_ty::#ident( #into_params ).into()
                     }
+
+                    #[no_mangle]
+                    #[doc(hidden)]
+                    #[allow(non_snake_case)]
+                    #[allow(unused_unsafe)]
+                    #[cfg(target_arch = "wasm32")]
+                    extern "C" fn #export_ident( #wasm_export_params ) {
+                        unsafe { #wasm_body }
+                    }
                 });
             }
+            syn::ImplItem::Const(implconst) => {
+                let const_attrs = match PolyAttr::take(&mut implconst.attrs) {
+                    Ok(const_attrs) => const_attrs,
+                    Err(e) => return e.to_compile_error(),
+                };
+                if let Some(error) = const_attrs.check(&["skip", "include"]) {
+                    return error;
+                }
+
+                match is_exported(&implconst.vis, &const_attrs) {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(e) => return e.to_compile_error(),
+                }
+
+                // associated consts become constants on the class of the struct
+                let ident = &implconst.ident;
+                let ty = &implconst.ty;
+                let docs = docs(&implconst.attrs);
+                let value = quote_spanned! { ty.span() =>
+                    || <#ty as ::polygen::__private::ExportedPolyValue>::poly_value(<#self_ty>::#ident)
+                };
+                polyconsts.push(quote! {
+                    ::polygen::items::PolyConst {
+                        module: module_path!(),
+                        name: stringify!(#ident),
+                        docs: #docs,
+                        ty: <#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                        value: #value,
+                    }
+                });
+            }
+            // associated types can not be used from foreign code
+            syn::ImplItem::Type(impltype) => {
+                let type_attrs = match PolyAttr::take(&mut impltype.attrs) {
+                    Ok(type_attrs) => type_attrs,
+                    Err(e) => return e.to_compile_error(),
+                };
+                if let Some(option) = type_attrs.first() {
+                    return syn::Error::new_spanned(
+                        option,
+                        "Associated types can not be exported by #[polygen]",
+                    )
+                    .to_compile_error();
+                }
+            }
             _ => {
                 return quote_spanned! { implitem.span() =>
                     compile_error!("This item is not supported by #[polygen]");
@@ -153,13 +286,58 @@ pub fn polyimpl(_attrs: &PolyAttr, item: &syn::ItemImpl) -> proc_macro2::TokenSt
         }
     }
 
+    // blocks with only private helpers or associated types have nothing to register
+    if polyfns.is_empty() && polyconsts.is_empty() {
+        return quote!();
+    }
+
+    // every impl block is submitted on its own, so a struct can have any number of them
+    // manual blocks are only registered by `PolyBag::register_impl`
+    let manual = attrs.has("manual");
     quote! {
         #exports
 
-        unsafe impl ::polygen::__private::ExportedPolyImpl for #self_ty {
-            const IMPL: ::polygen::items::PolyImpl = ::polygen::items::PolyImpl {
-                functions: &[#polyfns],
+        const _: () = {
+            const BLOCK: ::polygen::__private::PolyImplBlock = ::polygen::__private::PolyImplBlock {
+                module: module_path!(),
+                line: line!(),
+                ty: <#self_ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                polyimpl: ::polygen::items::PolyImpl {
+                    functions: &[#polyfns],
+                    constants: &[#polyconsts],
+                },
             };
-        }
+
+            fn register(bag: ::polygen::PolyBag) -> ::polygen::PolyBag {
+                ::polygen::__private::register_impl_block(bag, &BLOCK)
+            }
+
+            ::polygen::__private::inventory::submit! { BLOCK }
+            ::polygen::__private::inventory::submit! {
+                ::polygen::__private::PolyRegistration {
+                    module: module_path!(),
+                    line: line!(),
+                    manual: #manual,
+                    register,
+                }
+            }
+        };
+    }
+}
+
+/// Only public items are exported, unless they are marked with `skip` or `include`.
+fn is_exported(vis: &syn::Visibility, attrs: &PolyAttr) -> syn::Result<bool> {
+    let public = matches!(vis, syn::Visibility::Public(_));
+    let include = attrs.iter().find(|item| *item == "include");
+    match (attrs.has("skip"), include) {
+        (true, Some(include)) => Err(syn::Error::new_spanned(
+            include,
+            "`skip` and `include` can not be used on the same item",
+        )),
+        (false, Some(include)) if public => Err(syn::Error::new_spanned(
+            include,
+            "`include` is only needed on items that are not `pub`",
+        )),
+        (skip, include) => Ok(!skip && (public || include.is_some())),
     }
 }
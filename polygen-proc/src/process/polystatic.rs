@@ -0,0 +1,77 @@
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use super::{docs, export_ident, PolyAttr};
+
+pub fn polystatic(attrs: &PolyAttr, item: &syn::ItemStatic) -> proc_macro2::TokenStream {
+    if let Some(error) = attrs.check(&["trivial", "manual"]) {
+        return error;
+    }
+
+    if let syn::StaticMutability::Mut(token) = &item.mutability {
+        return quote_spanned! { token.span() =>
+            compile_error!("Mutable statics are not supported by #[polygen] attribute");
+        };
+    }
+
+    let ident = &item.ident;
+    let ty = &item.ty;
+    let docs = docs(&item.attrs);
+    let trivial = attrs.has("trivial");
+    let export_ident = export_ident("__polygen_static", ident, ty);
+
+    // statics are exported as a function without inputs that returns a copy of the value
+    let manual = attrs.has("manual");
+    let register = quote! {
+        ::polygen::__private::inventory::submit! {
+            ::polygen::__private::PolyRegistration {
+                module: module_path!(),
+                line: line!(),
+                manual: #manual,
+                register: ::polygen::PolyBag::register_function::<#ident>,
+            }
+        }
+    };
+
+    let exported_ty = quote_spanned! { ty.span() =>
+        <#ty as ::polygen::__private::ExportedPolyStruct>::ExportedType
+    };
+
+    quote! {
+        #register
+
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        pub struct #ident {}
+        unsafe impl ::polygen::__private::ExportedPolyFn for #ident {
+            const FUNCTION: ::polygen::items::PolyFn = ::polygen::items::PolyFn {
+                module: module_path!(),
+                name: stringify!(#ident),
+                docs: #docs,
+                export_name: stringify!(#export_ident),
+                trivial: #trivial,
+                params: ::polygen::items::FnParams {
+                    inputs: &[],
+                    output: Some(<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT),
+                }
+            };
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #[allow(improper_ctypes_definitions)]
+        #[cfg(not(target_arch = "wasm32"))]
+        extern "C" fn #export_ident() -> #exported_ty {
+            ::core::clone::Clone::clone(&#ident).into()
+        }
+
+        #[no_mangle]
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #[cfg(target_arch = "wasm32")]
+        extern "C" fn #export_ident(__polygen_out: *mut #exported_ty) {
+            unsafe { __polygen_out.write(::core::clone::Clone::clone(&#ident).into()) }
+        }
+    }
+}
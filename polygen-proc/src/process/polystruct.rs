@@ -1,9 +1,13 @@
 use quote::{quote, quote_spanned, TokenStreamExt};
 use syn::spanned::Spanned;
 
-use super::PolyAttr;
+use super::{docs, PolyAttr};
+
+pub fn polystruct(attrs: &PolyAttr, item: &mut syn::ItemStruct) -> proc_macro2::TokenStream {
+    if let Some(error) = attrs.check(&["manual", "opaque"]) {
+        return error;
+    }
 
-pub fn polystruct(_attrs: &PolyAttr, item: &syn::ItemStruct) -> proc_macro2::TokenStream {
     // fail on generics
     if !item.generics.params.empty_or_trailing() {
         return quote_spanned! { item.generics.params.span() =>
@@ -13,7 +17,13 @@ pub fn polystruct(_attrs: &PolyAttr, item: &syn::ItemStruct) -> proc_macro2::Tok
 
     // get useful items
     let ident = &item.ident;
-    let fields = &item.fields;
+    let struct_docs = docs(&item.attrs);
+    let manual = attrs.has("manual");
+    if attrs.has("opaque") {
+        return opaque(ident, &struct_docs, manual);
+    }
+
+    let fields = &mut item.fields;
     let export_ident = syn::Ident::new(&format!("__polygen_struct_{ident}"), ident.span());
 
     // create initial output stream
@@ -49,7 +59,18 @@ pub fn polystruct(_attrs: &PolyAttr, item: &syn::ItemStruct) -> proc_macro2::Tok
         F::Named(f) => {
             let mut from_fields = quote!();
             let mut into_fields = quote!();
-            for field in f.named.iter() {
+            for field in f.named.iter_mut() {
+                // collect and remove the helper attributes on the field
+                let field_attrs = match PolyAttr::take(&mut field.attrs) {
+                    Ok(field_attrs) => field_attrs,
+                    Err(e) => return e.to_compile_error(),
+                };
+                if let Some(error) = field_attrs.check(&["readonly"]) {
+                    return error;
+                }
+
+                let field_readonly = field_attrs.has("readonly");
+                let field_docs = docs(&field.attrs);
                 let field_vis = match field.vis {
                     syn::Visibility::Public(_) => true,
                     _ => false,
@@ -70,7 +91,10 @@ pub fn polystruct(_attrs: &PolyAttr, item: &syn::ItemStruct) -> proc_macro2::Tok
                 poly_fields.append_all(quote_spanned! { field_type.span() =>
                     ::polygen::items::StructField {
                         visible: #field_vis,
+                        readonly: #field_readonly,
                         name: stringify!(#field_name),
+                        docs: #field_docs,
+                        offset: ::core::mem::offset_of!(#export_ident, #field_name),
                         ty: ::polygen::items::FieldType::Typed(
                             &<#field_type as ::polygen::__private::ExportedPolyStruct>::STRUCT
                         ),
@@ -88,11 +112,29 @@ pub fn polystruct(_attrs: &PolyAttr, item: &syn::ItemStruct) -> proc_macro2::Tok
                         #ident { #into_fields }
                     }
                 }
+                // methods are called through a pointer to the struct itself
+                impl ::polygen::__private::PolyReceiver<#ident> for #export_ident {
+                    type Receiver = #ident;
+
+                    fn receiver(receiver: &mut #ident) -> &mut #ident {
+                        receiver
+                    }
+                }
             });
         }
     };
 
+    // submit the struct to the crate registry so it is exported even if no function uses it
     output.append_all(quote! {
+        ::polygen::__private::inventory::submit! {
+            ::polygen::__private::PolyRegistration {
+                module: module_path!(),
+                line: line!(),
+                manual: #manual,
+                register: ::polygen::PolyBag::register_struct::<#ident>,
+            }
+        }
+
         #[repr(C)]
         #[doc(hidden)]
         pub struct #export_ident {
@@ -105,11 +147,49 @@ pub fn polystruct(_attrs: &PolyAttr, item: &syn::ItemStruct) -> proc_macro2::Tok
             const STRUCT: ::polygen::items::PolyType = ::polygen::items::PolyType::Struct(::polygen::items::PolyStruct {
                 module: module_path!(),
                 name: stringify!(#ident),
+                docs: #struct_docs,
+                size: ::core::mem::size_of::<#export_ident>(),
+                align: ::core::mem::align_of::<#export_ident>(),
                 fields: &[#poly_fields],
                 generics: &[], // only manually implemented generics are currently supported
+                kind: ::polygen::items::StructKind::Data,
             });
         }
     });
 
     output
 }
+
+/// Exports a struct with any fields as a handle to a heap allocated value.
+///
+/// The fields stay hidden from foreign code, which only calls the methods of the struct.
+fn opaque(ident: &syn::Ident, docs: &str, manual: bool) -> proc_macro2::TokenStream {
+    quote! {
+        ::polygen::__private::inventory::submit! {
+            ::polygen::__private::PolyRegistration {
+                module: module_path!(),
+                line: line!(),
+                manual: #manual,
+                register: ::polygen::PolyBag::register_struct::<#ident>,
+            }
+        }
+
+        unsafe impl ::polygen::__private::ExportedPolyStruct for #ident {
+            type ExportedType = ::polygen::items::types::PolyHandle<#ident>;
+
+            const STRUCT: ::polygen::items::PolyType =
+                ::polygen::items::types::PolyHandle::<#ident>::describe(
+                    module_path!(),
+                    stringify!(#ident),
+                    #docs,
+                );
+            const CONSUMES_HANDLE: bool = true;
+        }
+
+        impl From<::polygen::items::types::PolyHandle<#ident>> for #ident {
+            fn from(value: ::polygen::items::types::PolyHandle<#ident>) -> Self {
+                value.into_inner()
+            }
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use quote::{quote, quote_spanned, ToTokens, TokenStreamExt};
+use syn::{punctuated::Punctuated, spanned::Spanned, Token};
+
+use super::{docs, export_ident, signature_types, take_docs, PolyAttr};
+
+pub fn polytrait(attrs: &PolyAttr, item: &mut syn::ItemTrait) -> proc_macro2::TokenStream {
+    if let Some(error) = attrs.check(&["manual", "export", "trust_thread_safe"]) {
+        return error;
+    }
+
+    // fail on generics
+    if !item.generics.params.empty_or_trailing() {
+        return quote_spanned! { item.generics.params.span() =>
+            compile_error!("Generics are not supported by #[polygen] attribute");
+        };
+    }
+
+    let ident = &item.ident;
+    let vis = &item.vis;
+    let trait_docs = docs(&item.attrs);
+    let vtable_ident = syn::Ident::new(&format!("{ident}VTable"), ident.span());
+    let foreign_ident = syn::Ident::new(&format!("Foreign{ident}"), ident.span());
+    let manual = attrs.has("manual");
+    let export = attrs.has("export");
+
+    let handle_ty = quote!( ::polygen::items::types::DynHandle<dyn #ident> );
+
+    let make_exp = |t: &syn::Type| {
+        quote_spanned! { t.span() =>
+            <#t as ::polygen::__private::ExportedPolyStruct>::ExportedType
+        }
+    };
+
+    let mut vtable_fields = quote!();
+    let mut poly_fields = quote!();
+    let mut trait_fns = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+    let mut methods = quote!();
+    let mut dyn_exports = quote!();
+    let mut dyn_fns = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+    for trait_item in &mut item.items {
+        let syn::TraitItem::Fn(trait_fn) = trait_item else {
+            return quote_spanned! { trait_item.span() =>
+                compile_error!("Only methods are supported in traits by #[polygen]");
+            };
+        };
+
+        // fail on generics
+        if !trait_fn.sig.generics.params.empty_or_trailing() {
+            return quote_spanned! { trait_fn.sig.generics.params.span() =>
+                compile_error!("Generics are not supported by #[polygen] attribute");
+            };
+        }
+
+        // foreign code only has the user data to call the method on
+        let receiver = match trait_fn.sig.receiver() {
+            Some(rec) if rec.reference.is_some() => rec.clone(),
+            _ => {
+                return quote_spanned! { trait_fn.sig.span() =>
+                    compile_error!("Trait methods must take `&self` or `&mut self` to be implemented by foreign code");
+                };
+            }
+        };
+
+        let fn_ident = &trait_fn.sig.ident;
+        let fn_docs = docs(&trait_fn.attrs);
+        let mut arg_types = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut call_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut sig_inputs = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut poly_inputs = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut ptr_inputs = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut dyn_params = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut dyn_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut wasm_params = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        let mut wasm_args = Punctuated::<proc_macro2::TokenStream, Token![,]>::new();
+        dyn_params.push(quote!( mut __polygen_self: #handle_ty ));
+        wasm_params.push(quote!( __polygen_self: *const #handle_ty ));
+        arg_types.push(quote!(*mut ::core::ffi::c_void));
+        call_args.push(quote!(self.vtable.user_data));
+        sig_inputs.push(receiver.to_token_stream());
+        ptr_inputs.push(quote! {
+            <*mut ::core::ffi::c_void as ::polygen::__private::ExportedPolyStruct>::STRUCT
+        });
+        for input in &mut trait_fn.sig.inputs {
+            let syn::FnArg::Typed(typed) = input else {
+                continue;
+            };
+
+            let input_docs = take_docs(&mut typed.attrs);
+            let ty = &typed.ty;
+            let pat_ident = match &*typed.pat {
+                syn::Pat::Ident(ident) => &ident.ident,
+                pat => {
+                    let message = format!(
+                        "This pattern is unsupported by #[polygen]. \
+                        Please use a literal name e.g. `literal_name: {}`",
+                        ty.to_token_stream()
+                    );
+
+                    return quote_spanned! { pat.span() =>
+                        compile_error!(#message);
+                    };
+                }
+            };
+
+            let export_ty = make_exp(ty);
+            dyn_params.push(quote!( #pat_ident: #export_ty ));
+            wasm_params.push(quote!( #pat_ident: *const #export_ty ));
+            dyn_args.push(quote_spanned!( ty.span() => #pat_ident.into() ));
+            wasm_args.push(quote_spanned!( ty.span() => ::core::ptr::read(#pat_ident).into() ));
+            arg_types.push(export_ty);
+            call_args.push(quote_spanned!( ty.span() => #pat_ident.into() ));
+            sig_inputs.push(quote!( #pat_ident: #ty ));
+            ptr_inputs.push(quote_spanned! { ty.span() =>
+                <#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT
+            });
+            poly_inputs.push(quote_spanned! { ty.span() =>
+                ::polygen::items::FnInput {
+                    name: stringify!(#pat_ident),
+                    docs: #input_docs,
+                    ty: &<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT,
+                    consumed: <#ty as ::polygen::__private::ExportedPolyStruct>::CONSUMES_HANDLE,
+                }
+            });
+        }
+
+        let (fn_output, export_output, ptr_output, polyout) = match &trait_fn.sig.output {
+            syn::ReturnType::Default => (quote!(), quote!(), quote!(None), quote!(None)),
+            syn::ReturnType::Type(_, ty) => {
+                let export_ty = make_exp(ty);
+                (
+                    quote!( -> #ty ),
+                    quote!( -> #export_ty ),
+                    quote_spanned! { ty.span() =>
+                        Some(&<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT)
+                    },
+                    quote_spanned! { ty.span() =>
+                        Some(<#ty as ::polygen::__private::ExportedPolyStruct>::STRUCT)
+                    },
+                )
+            }
+        };
+
+        vtable_fields.append_all(quote! {
+            pub #fn_ident: extern "C" fn( #arg_types ) #export_output,
+        });
+        poly_fields.append_all(quote! {
+            ::polygen::items::StructField {
+                visible: false,
+                readonly: false,
+                name: stringify!(#fn_ident),
+                docs: "",
+                offset: ::core::mem::offset_of!(#vtable_ident, #fn_ident),
+                ty: ::polygen::items::FieldType::Typed(&::polygen::items::PolyType::FnPtr(
+                    ::polygen::items::PolyFnPtr {
+                        inputs: &[#ptr_inputs],
+                        output: #ptr_output,
+                    }
+                )),
+            },
+        });
+        trait_fns.push(quote! {
+            ::polygen::items::TraitFn {
+                name: stringify!(#fn_ident),
+                docs: #fn_docs,
+                params: ::polygen::items::FnParams {
+                    inputs: &[#poly_inputs],
+                    output: #polyout,
+                },
+            }
+        });
+        methods.append_all(quote! {
+            fn #fn_ident( #sig_inputs ) #fn_output {
+                (self.vtable.#fn_ident)( #call_args ).into()
+            }
+        });
+
+        if !export {
+            continue;
+        }
+
+        // exported trait objects get a native function for every method
+        let object = match receiver.mutability {
+            Some(_) => quote!(object_mut),
+            None => quote!(object),
+        };
+        let call = quote!( __polygen_self.#object().#fn_ident( #dyn_args ) );
+        let wasm_call = quote!( __polygen_self.#object().#fn_ident( #wasm_args ) );
+        let wasm_body = match &trait_fn.sig.output {
+            syn::ReturnType::Default => quote!( #wasm_call; ),
+            syn::ReturnType::Type(_, ty) => {
+                // wasm exports write their output through a pointer
+                let export_ty = make_exp(ty);
+                wasm_params.insert(0, quote!( __polygen_out: *mut #export_ty ));
+                quote!( __polygen_out.write(#wasm_call.into()); )
+            }
+        };
+
+        let signature = signature_types(&trait_fn.sig);
+        let export_ident = export_ident(
+            &format!("__polygen_dynfn_{ident}"),
+            fn_ident,
+            quote!( #ident #signature ),
+        );
+        dyn_fns.push(quote! {
+            ::polygen::items::ImplFn {
+                name: stringify!(#fn_ident),
+                docs: #fn_docs,
+                export_name: stringify!(#export_ident),
+                trivial: false,
+                params: ::polygen::items::FnParams {
+                    inputs: &[
+                        ::polygen::items::FnInput {
+                            name: "self",
+                            docs: "",
+                            ty: &<dyn #ident as ::polygen::__private::ExportedPolyDyn>::STRUCT,
+                            consumed: false,
+                        },
+                        #poly_inputs
+                    ],
+                    output: #polyout,
+                },
+            }
+        });
+        dyn_exports.append_all(quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            #[allow(improper_ctypes_definitions)]
+            #[cfg(not(target_arch = "wasm32"))]
+            extern "C" fn #export_ident( #dyn_params ) #export_output {
+                unsafe { #call }.into()
+            }
+
+            #[no_mangle]
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            #[allow(unused_unsafe)]
+            #[cfg(target_arch = "wasm32")]
+            extern "C" fn #export_ident( #wasm_params ) {
+                unsafe {
+                    let mut __polygen_self = ::core::ptr::read(__polygen_self);
+                    #wasm_body
+                }
+            }
+        });
+    }
+
+    let dyn_export = match export {
+        false => quote!(),
+        true => {
+            let dyn_name = format!("Dyn{ident}");
+            quote! {
+                #dyn_exports
+
+                unsafe impl ::polygen::__private::ExportedPolyDyn for dyn #ident {
+                    const STRUCT: ::polygen::items::PolyType = ::polygen::items::PolyType::Struct(::polygen::items::PolyStruct {
+                        module: module_path!(),
+                        name: #dyn_name,
+                        docs: #trait_docs,
+                        size: ::core::mem::size_of::<#handle_ty>(),
+                        align: ::core::mem::align_of::<#handle_ty>(),
+                        fields: <#handle_ty>::FIELDS,
+                        generics: &[],
+                        kind: ::polygen::items::StructKind::Handle(Some(
+                            &<#foreign_ident as ::polygen::__private::ExportedPolyStruct>::STRUCT
+                        )),
+                    });
+                }
+
+                impl From<#handle_ty> for Box<dyn #ident> {
+                    fn from(value: #handle_ty) -> Self {
+                        value.into_box()
+                    }
+                }
+
+                const _: () = {
+                    const BLOCK: ::polygen::__private::PolyImplBlock = ::polygen::__private::PolyImplBlock {
+                        module: module_path!(),
+                        line: line!(),
+                        ty: <dyn #ident as ::polygen::__private::ExportedPolyDyn>::STRUCT,
+                        polyimpl: ::polygen::items::PolyImpl {
+                            functions: &[#dyn_fns],
+                            constants: &[],
+                        },
+                    };
+
+                    fn register(bag: ::polygen::PolyBag) -> ::polygen::PolyBag {
+                        ::polygen::__private::register_impl_block(bag, &BLOCK)
+                    }
+
+                    ::polygen::__private::inventory::submit! { BLOCK }
+                    ::polygen::__private::inventory::submit! {
+                        ::polygen::__private::PolyRegistration {
+                            module: module_path!(),
+                            line: line!(),
+                            manual: #manual,
+                            register,
+                        }
+                    }
+                };
+            }
+        }
+    };
+
+    // the compiler can not check foreign code, so thread safety has to be asserted explicitly
+    let trust_thread_safe = attrs.iter().find(|item| *item == "trust_thread_safe");
+    let mut thread_safety = quote!();
+    let mut thread_safe_bounds = Vec::new();
+    for bound in &item.supertraits {
+        let syn::TypeParamBound::Trait(bound) = bound else {
+            continue;
+        };
+
+        let Some(marker) = ["Send", "Sync"]
+            .into_iter()
+            .find(|m| bound.path.is_ident(m))
+        else {
+            continue;
+        };
+
+        if trust_thread_safe.is_none() {
+            let message = format!(
+                "Foreign implementations of `{ident}` can not be proven to be `{marker}`. \
+                Add #[polygen(trust_thread_safe)] to trust foreign code to be thread safe"
+            );
+            return quote_spanned! { bound.span() =>
+                compile_error!(#message);
+            };
+        }
+
+        let marker = syn::Ident::new(marker, bound.span());
+        thread_safety.append_all(quote!( unsafe impl #marker for #foreign_ident {} ));
+        thread_safe_bounds.push(format!("`{marker}`"));
+    }
+
+    if let (Some(trust), true) = (trust_thread_safe, thread_safe_bounds.is_empty()) {
+        return quote_spanned! { trust.span() =>
+            compile_error!("`trust_thread_safe` requires the trait to require `Send` or `Sync`");
+        };
+    }
+
+    let vtable_docs = format!("The functions foreign code provides to implement [`{ident}`].");
+    let mut foreign_docs = format!("A [`{ident}`] implemented by foreign code through its vtable.");
+    if !thread_safe_bounds.is_empty() {
+        let bounds = thread_safe_bounds.join(" and ");
+        foreign_docs += &format!(
+            "\n\n# Safety\n\
+            This type is {bounds} because of `#[polygen(trust_thread_safe)]`. \
+            Foreign code must make every function of the vtable safe to call from any thread, \
+            concurrently for `&self` methods, and `user_data` must be safe to use and drop on any thread."
+        );
+    }
+    quote! {
+        ::polygen::__private::inventory::submit! {
+            ::polygen::__private::PolyRegistration {
+                module: module_path!(),
+                line: line!(),
+                manual: #manual,
+                register: ::polygen::PolyBag::register_struct::<#foreign_ident>,
+            }
+        }
+
+        #[doc = #vtable_docs]
+        ///
+        /// `user_data` is passed as the first argument of every function
+        /// and released by `drop` once the implementation is dropped.
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        #vis struct #vtable_ident {
+            pub user_data: *mut ::core::ffi::c_void,
+            #vtable_fields
+            pub drop: Option<extern "C" fn(*mut ::core::ffi::c_void)>,
+        }
+
+        #[doc = #foreign_docs]
+        #vis struct #foreign_ident {
+            vtable: #vtable_ident,
+        }
+
+        impl #foreign_ident {
+            pub fn new(vtable: #vtable_ident) -> Self {
+                Self { vtable }
+            }
+        }
+
+        impl #ident for #foreign_ident {
+            #methods
+        }
+
+        impl Drop for #foreign_ident {
+            fn drop(&mut self) {
+                if let Some(drop) = self.vtable.drop {
+                    drop(self.vtable.user_data);
+                }
+            }
+        }
+
+        #thread_safety
+
+        #dyn_export
+
+        impl From<#vtable_ident> for #foreign_ident {
+            fn from(vtable: #vtable_ident) -> Self {
+                Self { vtable }
+            }
+        }
+
+        impl From<#foreign_ident> for #vtable_ident {
+            fn from(value: #foreign_ident) -> Self {
+                // the foreign side takes back ownership of the user data
+                let value = ::core::mem::ManuallyDrop::new(value);
+                value.vtable
+            }
+        }
+
+        unsafe impl ::polygen::__private::ExportedPolyStruct for #foreign_ident {
+            type ExportedType = #vtable_ident;
+
+            const STRUCT: ::polygen::items::PolyType = ::polygen::items::PolyType::Struct(::polygen::items::PolyStruct {
+                module: module_path!(),
+                name: stringify!(#ident),
+                docs: #trait_docs,
+                size: ::core::mem::size_of::<#vtable_ident>(),
+                align: ::core::mem::align_of::<#vtable_ident>(),
+                fields: &[
+                    ::polygen::items::StructField {
+                        visible: false,
+                        readonly: false,
+                        name: "user_data",
+                        docs: "",
+                        offset: ::core::mem::offset_of!(#vtable_ident, user_data),
+                        ty: ::polygen::items::FieldType::Typed(
+                            &<*mut ::core::ffi::c_void as ::polygen::__private::ExportedPolyStruct>::STRUCT
+                        ),
+                    },
+                    #poly_fields
+                    ::polygen::items::StructField {
+                        visible: false,
+                        readonly: false,
+                        name: "drop",
+                        docs: "",
+                        offset: ::core::mem::offset_of!(#vtable_ident, drop),
+                        ty: ::polygen::items::FieldType::Typed(
+                            &<extern "C" fn(*mut ::core::ffi::c_void) as ::polygen::__private::ExportedPolyStruct>::STRUCT
+                        ),
+                    },
+                ],
+                generics: &[],
+                kind: ::polygen::items::StructKind::VTable(&[#trait_fns]),
+            });
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::sync::OnceLock;
+
+use crate::PolyBag;
+
+/// Returns the json of a bag containing every registered item.
+///
+/// Used by the function exported with [`export_bag!`](crate::export_bag).
+pub fn embedded_bag_json(name: &str) -> &'static str {
+    static JSON: OnceLock<String> = OnceLock::new();
+    JSON.get_or_init(|| {
+        PolyBag::new(name)
+            .register_all()
+            .to_json()
+            .expect("registered items are always valid json")
+    })
+}
@@ -1,7 +1,13 @@
+mod embed;
+mod polyconst;
 mod polyfn;
 mod polyimpl;
 mod polystruct;
+mod registry;
 
+pub use embed::*;
+pub use polyconst::*;
 pub use polyfn::*;
 pub use polyimpl::*;
 pub use polystruct::*;
+pub use registry::*;
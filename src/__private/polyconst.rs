@@ -0,0 +1,16 @@
+use crate::items::{PolyConst, PolyValue};
+
+use super::ExportedPolyStruct;
+
+/// A constant or static exported by `#[polygen]`.
+///
+/// # Safety
+/// `CONST` must describe the type of the exported value.
+pub unsafe trait ExportedPolyConst: Sized + 'static {
+    const CONST: PolyConst;
+}
+
+/// A type whose values can be recorded as constants.
+pub trait ExportedPolyValue: ExportedPolyStruct {
+    fn poly_value(self) -> PolyValue;
+}
@@ -1,7 +1,38 @@
-use crate::items::PolyImpl;
-
 use super::ExportedPolyStruct;
+use crate::{
+    items::{PolyImpl, PolyType},
+    PolyBag,
+};
+
+/// An impl block annotated with `#[polygen]`, a struct can have any number of them.
+pub struct PolyImplBlock {
+    pub module: &'static str,
+    pub line: u32,
+    pub ty: PolyType,
+    pub polyimpl: PolyImpl,
+}
+
+inventory::collect!(PolyImplBlock);
+
+/// Resolves the receiver of methods taking `&self` or `&mut self` from the exported type of `T`.
+///
+/// Foreign code passes a pointer to the receiver, which is `T` itself for `#[repr(C)]` structs
+/// and the handle for types exported as a [`PolyHandle`](crate::items::types::PolyHandle).
+pub trait PolyReceiver<T> {
+    type Receiver: ExportedPolyStruct;
+
+    fn receiver(receiver: &mut Self::Receiver) -> &mut T;
+}
+
+impl<T: ExportedPolyStruct> PolyReceiver<T> for T {
+    type Receiver = T;
+
+    fn receiver(receiver: &mut Self::Receiver) -> &mut T {
+        receiver
+    }
+}
 
-pub unsafe trait ExportedPolyImpl: ExportedPolyStruct {
-    const IMPL: PolyImpl;
+/// Registers a single impl block, so manual blocks of the same struct stay unregistered.
+pub fn register_impl_block(bag: PolyBag, block: &PolyImplBlock) -> PolyBag {
+    bag.insert_impl_block(block)
 }
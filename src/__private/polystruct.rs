@@ -3,4 +3,18 @@ use crate::items::PolyType;
 pub unsafe trait ExportedPolyStruct: Sized + 'static {
     type ExportedType: From<Self> + Into<Self>;
     const STRUCT: PolyType;
+    /// Whether passing the type by value moves the native value out of its foreign handle.
+    ///
+    /// Foreign code invalidates such handles after the call.
+    const CONSUMES_HANDLE: bool = false;
+}
+
+/// Implemented for `dyn Trait` by traits marked with `#[polygen(export)]`.
+///
+/// `Box<dyn Trait>` and `Arc<dyn Trait>` are then exported as a handle described by `STRUCT`.
+///
+/// # Safety
+/// `STRUCT` must describe the layout of [`DynHandle<Self>`](crate::items::types::DynHandle).
+pub unsafe trait ExportedPolyDyn: 'static {
+    const STRUCT: PolyType;
 }
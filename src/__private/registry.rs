@@ -0,0 +1,35 @@
+use crate::PolyBag;
+
+pub use inventory;
+
+/// An item submitted to the crate registry by the `#[polygen]` attribute.
+pub struct PolyRegistration {
+    pub module: &'static str,
+    pub line: u32,
+    pub manual: bool,
+    pub register: fn(PolyBag) -> PolyBag,
+}
+
+inventory::collect!(PolyRegistration);
+
+/// Resolves a module path written at `caller` into a full module path.
+pub fn resolve_module_path(caller: &str, path: &str) -> String {
+    let mut segments = path.split("::").map(str::trim).filter(|s| !s.is_empty());
+    let mut resolved = caller.split("::").collect::<Vec<_>>();
+    match segments.next() {
+        Some("crate") => resolved.truncate(1),
+        Some("self") => (),
+        Some("super") => _ = resolved.pop(),
+        Some(first) => resolved = vec![first],
+        None => (),
+    }
+
+    for segment in segments {
+        match segment {
+            "super" => _ = resolved.pop(),
+            segment => resolved.push(segment),
+        }
+    }
+
+    resolved.join("::")
+}
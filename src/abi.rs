@@ -0,0 +1,221 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+};
+
+use crate::{
+    __private::PolyRegistration,
+    ir::{FieldType, FnParams, ItemKey, PolyStruct, PolyType},
+    PolyBag, PolyMod,
+};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A FNV-1a hasher whose output is stable across processes and compiler versions.
+struct AbiHasher {
+    hash: u64,
+    /// Structs whose layout was already hashed, later uses only hash their name.
+    visited: HashSet<ItemKey>,
+}
+
+impl AbiHasher {
+    fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET,
+            visited: HashSet::new(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write(&(value as u64).to_le_bytes());
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.write(value.as_bytes());
+    }
+
+    fn write_type(&mut self, t: &PolyType) {
+        match t {
+            PolyType::Primitive(p) => {
+                self.write_str("primitive");
+                self.write_str(p);
+            }
+            PolyType::Pointer(t) => {
+                self.write_str("pointer");
+                self.write_type(t);
+            }
+            // recursive types terminate at the first struct that was already visited
+            PolyType::Struct(s) if !self.visited.insert(s.key()) => {
+                self.write_str("visited");
+                self.write_name(t);
+            }
+            PolyType::Struct(s) => {
+                self.write_str("struct");
+                self.write_struct(s);
+            }
+            PolyType::FnPtr(f) => {
+                self.write_str("fn_ptr");
+                self.write_usize(f.inputs.len());
+                for input in &f.inputs {
+                    self.write_type(input);
+                }
+
+                match &f.output {
+                    None => self.write_str("void"),
+                    Some(output) => self.write_type(output),
+                }
+            }
+        }
+    }
+
+    fn write_name(&mut self, t: &PolyType) {
+        match t {
+            PolyType::Primitive(p) => self.write_str(p),
+            PolyType::Pointer(t) => {
+                self.write_str("*");
+                self.write_name(t);
+            }
+            PolyType::Struct(s) => {
+                self.write_str(&s.module);
+                self.write_str(&s.name);
+                for generic in &s.generics {
+                    self.write_name(&generic.ty);
+                }
+            }
+            PolyType::FnPtr(f) => {
+                self.write_str("fn");
+                self.write_usize(f.inputs.len());
+                for input in &f.inputs {
+                    self.write_name(input);
+                }
+
+                match &f.output {
+                    None => self.write_str("void"),
+                    Some(output) => self.write_name(output),
+                }
+            }
+        }
+    }
+
+    fn write_struct(&mut self, s: &PolyStruct) {
+        self.write_str(&s.module);
+        self.write_str(&s.name);
+        self.write_str(&s.kind.to_string());
+        self.write_usize(s.size);
+        self.write_usize(s.align);
+        for field in &s.fields {
+            self.write_str(&field.name);
+            self.write_usize(field.offset);
+            match &field.ty {
+                FieldType::Typed(t) => self.write_type(t),
+                FieldType::Generic(ident) => self.write_str(ident),
+            }
+        }
+
+        for generic in &s.generics {
+            self.write_str(&generic.ident);
+            self.write_type(&generic.ty);
+        }
+    }
+}
+
+impl FnParams {
+    /// Returns a hash over the signature and the layout of every type used by the function.
+    ///
+    /// Generated bindings compare this with `__polygen_abi_fingerprint`
+    /// to detect that they were generated for a different version of the library.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = AbiHasher::new();
+        hasher.write_usize(self.inputs.len());
+        for input in &self.inputs {
+            hasher.write_type(&input.ty);
+        }
+
+        match &self.output {
+            None => hasher.write_str("void"),
+            Some(output) => hasher.write_type(output),
+        }
+
+        hasher.hash
+    }
+}
+
+impl PolyStruct {
+    /// Returns a hash over the layout of the struct and every type it refers to.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = AbiHasher::new();
+        hasher.write_type(&PolyType::Struct(self.clone()));
+        hasher.hash
+    }
+}
+
+impl PolyMod {
+    /// Returns the abi fingerprint of every function and struct in this module and its submodules.
+    ///
+    /// Functions are named by their export name and structs by their [`ItemKey`],
+    /// so registered structs that no function uses are verified too.
+    pub fn fingerprints(&self) -> Vec<(String, u64)> {
+        let mut fingerprints = Vec::new();
+        collect_functions(self, &mut fingerprints);
+        for s in self.reachable_structs(|_| true) {
+            fingerprints.push((s.key().to_string(), s.fingerprint()));
+        }
+
+        fingerprints
+    }
+}
+
+fn collect_functions(m: &PolyMod, fingerprints: &mut Vec<(String, u64)>) {
+    let impl_functions = m
+        .structs()
+        .flat_map(|(_, i)| i.into_iter().flat_map(|i| &i.functions))
+        .map(|f| (&f.export_name, &f.params));
+    let functions = m.functions().map(|f| (&f.export_name, &f.params));
+
+    for (name, params) in impl_functions.chain(functions) {
+        fingerprints.push((name.clone(), params.fingerprint()));
+    }
+
+    for m in m.modules() {
+        collect_functions(m, fingerprints);
+    }
+}
+
+/// The fingerprints of every exported function and struct, which bindings query once per item.
+fn fingerprints() -> &'static HashMap<Vec<u8>, u64> {
+    static FINGERPRINTS: OnceLock<HashMap<Vec<u8>, u64>> = OnceLock::new();
+    FINGERPRINTS.get_or_init(|| {
+        // manually registered items are part of the library too
+        let bag = inventory::iter::<PolyRegistration>
+            .into_iter()
+            .fold(PolyBag::new("abi"), |bag, item| (item.register)(bag));
+
+        let fingerprints = bag.root_module().fingerprints().into_iter();
+        fingerprints
+            .map(|(name, f)| (name.into_bytes(), f))
+            .collect()
+    })
+}
+
+/// Returns the fingerprint of the exported function or struct with the given name.
+///
+/// Returns `0` if the library does not contain an item with that name.
+#[no_mangle]
+#[doc(hidden)]
+pub unsafe extern "C" fn __polygen_abi_fingerprint(name: *const u8, len: usize) -> u64 {
+    if name.is_null() {
+        return 0;
+    }
+
+    let name = std::slice::from_raw_parts(name, len);
+    fingerprints().get(name).copied().unwrap_or(0)
+}
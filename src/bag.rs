@@ -1,17 +1,32 @@
+use std::collections::HashSet;
+
 use indexmap::{IndexMap, IndexSet};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    __private::{ExportedPolyFn, ExportedPolyImpl},
-    items::{FieldType, PolyFn, PolyImpl, PolyStruct, PolyType},
+    __private::{
+        ExportedPolyConst, ExportedPolyFn, ExportedPolyStruct, PolyImplBlock, PolyRegistration,
+    },
+    ir::{
+        FieldType, FnParams, IrError, ItemKey, PolyConst, PolyFn, PolyImpl, PolyStruct, PolyType,
+        StructKind, SCHEMA_VERSION,
+    },
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PolyBag {
     #[serde(flatten)]
     root_module: PolyMod,
 }
 
+/// The versioned json document written by [`PolyBag::to_json`].
+#[derive(Serialize, Deserialize)]
+struct BagDocument<B> {
+    version: u32,
+    #[serde(flatten)]
+    bag: B,
+}
+
 impl PolyBag {
     pub fn new(name: impl Into<String>) -> Self {
         let root_module = PolyMod::build(name);
@@ -22,90 +37,295 @@ impl PolyBag {
         &self.root_module
     }
 
-    pub fn register_impl<T: ExportedPolyImpl>(mut self) -> Self {
+    /// Serializes the bag into versioned json.
+    pub fn to_json(&self) -> Result<String, IrError> {
+        let document = BagDocument {
+            version: SCHEMA_VERSION,
+            bag: self,
+        };
+
+        Ok(serde_json::to_string_pretty(&document)?)
+    }
+
+    /// Reads a bag from json written by [`PolyBag::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, IrError> {
+        // check the version first so old documents fail with a useful error
+        let document: BagDocument<serde::de::IgnoredAny> = serde_json::from_str(json)?;
+        if document.version != SCHEMA_VERSION {
+            return Err(IrError::Version(document.version));
+        }
+
+        let document: BagDocument<PolyBag> = serde_json::from_str(json)?;
+        Ok(document.bag)
+    }
+
+    /// Registers every item annotated with `#[polygen]` that is linked into the binary.
+    ///
+    /// Items marked with `#[polygen(manual)]` are skipped and have to be registered by hand.
+    /// The `polygen` cli can only read these items from a compiled library
+    /// that calls [`export_bag!`](crate::export_bag).
+    pub fn register_all(self) -> Self {
+        registrations(|_| true).fold(self, |bag, item| (item.register)(bag))
+    }
+
+    /// Registers every item annotated with `#[polygen]` in a module and its submodules.
+    ///
+    /// `path` is a full module path like `my_crate::my_module`,
+    /// use [`register_module!`](crate::register_module) to resolve paths relative to the caller.
+    /// Items marked with `#[polygen(manual)]` are skipped.
+    pub fn register_module(self, path: &str) -> Self {
+        let in_module = |module: &str| match module.strip_prefix(path) {
+            Some(rest) => rest.is_empty() || rest.starts_with("::"),
+            None => false,
+        };
+
+        registrations(|item| in_module(item.module)).fold(self, |bag, item| (item.register)(bag))
+    }
+
+    /// Registers a struct and every struct it references, even if it is not used by any function.
+    pub fn register_struct<T: ExportedPolyStruct>(mut self) -> Self {
+        self.register_type(&PolyType::from(&T::STRUCT), &mut Vec::new());
+        self
+    }
+
+    pub fn register_const<T: ExportedPolyConst>(mut self) -> Self {
+        let constant = PolyConst::from(&T::CONST);
+        self.register_type(&constant.ty, &mut Vec::new());
+
+        let target_mod = self.root_module.get_target_mod(&constant.module);
+        target_mod.constants.insert(constant);
+        self
+    }
+
+    /// Registers a struct with the methods of every `#[polygen]` impl block it has.
+    ///
+    /// This includes impl blocks marked with `#[polygen(manual)]`.
+    pub fn register_impl<T: ExportedPolyStruct>(self) -> Self {
+        let key = T::STRUCT.key();
+        let mut blocks = inventory::iter::<PolyImplBlock>
+            .into_iter()
+            .filter(|block| block.ty.key() == key)
+            .collect::<Vec<_>>();
+        blocks.sort_by_key(|block| (block.module, block.line));
+
+        let bag = self.register_struct::<T>();
+        blocks
+            .into_iter()
+            .fold(bag, |bag, block| bag.insert_impl_block(block))
+    }
+
+    pub(crate) fn insert_impl_block(mut self, block: &PolyImplBlock) -> Self {
+        let polyimpl = PolyImpl::from(&block.polyimpl);
+
         // register the struct and impl
-        if let PolyType::Struct(s) = &T::STRUCT {
-            self.insert_struct_data(s, Some(T::IMPL));
+        if let PolyType::Struct(s) = PolyType::from(&block.ty) {
+            self.insert_struct_data(&s, Some(polyimpl.clone()), &mut Vec::new());
         }
 
-        // register all function types
-        for func in T::IMPL.functions {
-            for input in func.params.inputs {
-                if let PolyType::Struct(s) = input.ty {
-                    self.insert_struct_data(s, None);
-                }
-            }
+        // register all function and constant types
+        for func in &polyimpl.functions {
+            self.register_params(&func.params);
+        }
 
-            if let Some(out) = &func.params.output {
-                if let PolyType::Struct(s) = out {
-                    self.insert_struct_data(s, None);
-                }
-            }
+        for constant in &polyimpl.constants {
+            self.register_type(&constant.ty, &mut Vec::new());
         }
 
         self
     }
 
     pub fn register_function<T: ExportedPolyFn>(mut self) -> Self {
-        // get reference to the function
-        let func = &T::FUNCTION;
+        // convert the function into its owned form
+        let func = PolyFn::from(&T::FUNCTION);
 
-        // register all its inputs
-        for input in func.params.inputs {
-            if let PolyType::Struct(s) = input.ty {
-                self.insert_struct_data(s, None);
-            }
+        // register all its inputs and its output
+        self.register_params(&func.params);
+
+        // insert the function
+        let target_mod = self.root_module.get_target_mod(&func.module);
+        target_mod.functions.insert(func);
+        self
+    }
+
+    fn register_params(&mut self, params: &FnParams) {
+        let types = params.inputs.iter().map(|i| &i.ty).chain(&params.output);
+        for t in types {
+            self.register_type(t, &mut Vec::new());
         }
+    }
 
-        // register its output
-        if let Some(out) = &func.params.output {
-            if let PolyType::Struct(s) = out {
-                self.insert_struct_data(s, None);
+    /// Registers every struct reachable from a type.
+    ///
+    /// This follows pointers, generic arguments and fields to any depth.
+    /// `visiting` holds the structs currently being registered, so recursive structs terminate.
+    fn register_type(&mut self, t: &PolyType, visiting: &mut Vec<ItemKey>) {
+        match t {
+            PolyType::Primitive(_) => (),
+            PolyType::Pointer(t) => self.register_type(t, visiting),
+            PolyType::Struct(s) => self.insert_struct_data(s, None, visiting),
+            PolyType::FnPtr(f) => {
+                for t in f.inputs.iter().chain(f.output.as_deref()) {
+                    self.register_type(t, visiting);
+                }
             }
         }
-
-        // insert the function
-        let target_mod = self.root_module.get_target_mod(func.module);
-        target_mod.functions.insert(*func);
-        self
     }
 
-    fn insert_struct_data(&mut self, s: &PolyStruct, i: Option<PolyImpl>) {
-        // register all nested structs
-        for field in s.fields {
-            if let FieldType::Typed(PolyType::Struct(s)) = field.ty {
-                self.insert_struct_data(s, None);
+    fn insert_struct_data(
+        &mut self,
+        s: &PolyStruct,
+        i: Option<PolyImpl>,
+        visiting: &mut Vec<ItemKey>,
+    ) {
+        // a struct behind a pointer to itself is only a name, the outer struct is registered instead
+        let key = s.key();
+        if visiting.contains(&key) {
+            return;
+        }
+
+        // registered structs only need their impl
+        let target_mod = self.root_module.get_target_mod(&s.module);
+        if let Some(entry) = target_mod.structs.get_mut(s) {
+            match (entry, i) {
+                (Some(entry), Some(i)) => merge_impl(entry, i),
+                (entry, Some(i)) => *entry = Some(i),
+                (_, None) => (),
             }
+
+            return;
         }
 
-        // register all generic types
-        for generic in s.generics {
-            if let PolyType::Struct(s) = generic.ty {
-                self.insert_struct_data(s, None);
+        // register all nested structs and generic types first
+        visiting.push(key);
+        for field in &s.fields {
+            if let FieldType::Typed(t) = &field.ty {
+                self.register_type(t, visiting);
             }
         }
 
+        for generic in &s.generics {
+            self.register_type(&generic.ty, visiting);
+        }
+
+        // trait objects also need the interface of their trait
+        if let StructKind::Handle(Some(interface)) = &s.kind {
+            self.register_type(interface, visiting);
+        }
+
+        visiting.pop();
+
         // register current struct
-        let target_mod = self.root_module.get_target_mod(s.module);
-        use indexmap::map::Entry as E;
-        match target_mod.structs.entry(*s) {
-            E::Occupied(mut e) => {
-                if let Some(i) = i {
-                    e.insert(Some(i));
-                }
-            }
-            E::Vacant(e) => {
-                e.insert(i);
+        let target_mod = self.root_module.get_target_mod(&s.module);
+        target_mod.structs.insert(s.clone(), i);
+    }
+}
+
+/// Adds the functions of another impl block of the same struct.
+///
+/// Items that were already merged are skipped, so registering a struct twice is harmless.
+/// Every export name belongs to a single definition, while methods and constants that only share
+/// their name are kept, so [`PolyBag::validate`] reports them.
+fn merge_impl(existing: &mut PolyImpl, other: PolyImpl) {
+    for f in other.functions {
+        let merged = existing
+            .functions
+            .iter()
+            .any(|e| e.export_name == f.export_name);
+        if !merged {
+            existing.functions.push(f);
+        }
+    }
+
+    for c in other.constants {
+        let merged = existing
+            .constants
+            .iter()
+            .any(|e| e.name == c.name && e.ty == c.ty && e.value == c.value);
+        if !merged {
+            existing.constants.push(c);
+        }
+    }
+}
+
+/// Returns every automatically registered item matching `filter`, sorted by its location.
+fn registrations(
+    filter: impl Fn(&PolyRegistration) -> bool,
+) -> impl Iterator<Item = &'static PolyRegistration> {
+    // the registry is in link order, so sort it to keep the output stable
+    let mut items = inventory::iter::<PolyRegistration>
+        .into_iter()
+        .filter(|item| !item.manual && filter(item))
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| (item.module, item.line));
+    items.into_iter()
+}
+
+fn collect_module(
+    m: &PolyMod,
+    seed: &dyn Fn(&PolyStruct) -> bool,
+    found: &mut Vec<PolyStruct>,
+    seen: &mut HashSet<ItemKey>,
+) {
+    for (s, i) in m.structs() {
+        if seed(s) {
+            collect_struct(s, found, seen);
+        }
+
+        for f in i.iter().flat_map(|i| &i.functions) {
+            collect_params(&f.params, found, seen);
+        }
+    }
+
+    for f in m.functions() {
+        collect_params(&f.params, found, seen);
+    }
+
+    for m in m.modules() {
+        collect_module(m, seed, found, seen);
+    }
+}
+
+fn collect_params(params: &FnParams, found: &mut Vec<PolyStruct>, seen: &mut HashSet<ItemKey>) {
+    for t in params.inputs.iter().map(|i| &i.ty).chain(&params.output) {
+        collect_type(t, found, seen);
+    }
+}
+
+fn collect_type(t: &PolyType, found: &mut Vec<PolyStruct>, seen: &mut HashSet<ItemKey>) {
+    match t {
+        PolyType::Primitive(_) => (),
+        PolyType::Pointer(t) => collect_type(t, found, seen),
+        PolyType::FnPtr(f) => {
+            for t in f.inputs.iter().chain(f.output.as_deref()) {
+                collect_type(t, found, seen);
             }
         }
+        PolyType::Struct(s) => collect_struct(s, found, seen),
     }
 }
 
-#[derive(Debug, Serialize)]
+fn collect_struct(s: &PolyStruct, found: &mut Vec<PolyStruct>, seen: &mut HashSet<ItemKey>) {
+    if !seen.insert(s.key()) {
+        return;
+    }
+
+    found.push(s.clone());
+    for field in &s.fields {
+        collect_type(s.resolve_field(&field.ty), found, seen);
+    }
+
+    for generic in &s.generics {
+        collect_type(&generic.ty, found, seen);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PolyMod {
     name: String,
+    constants: IndexSet<PolyConst>,
     functions: IndexSet<PolyFn>,
     modules: IndexMap<String, PolyMod>,
+    #[serde(with = "struct_entries")]
     structs: IndexMap<PolyStruct, Option<PolyImpl>>,
 }
 
@@ -113,6 +333,7 @@ impl PolyMod {
     fn build(name: impl Into<String>) -> Self {
         Self {
             name: name.into(),
+            constants: Default::default(),
             structs: Default::default(),
             functions: Default::default(),
             modules: Default::default(),
@@ -127,6 +348,10 @@ impl PolyMod {
         self.structs.iter().map(|(s, i)| (s, i.as_ref()))
     }
 
+    pub fn constants(&self) -> impl Iterator<Item = &PolyConst> {
+        self.constants.iter()
+    }
+
     pub fn functions(&self) -> impl Iterator<Item = &PolyFn> {
         self.functions.iter()
     }
@@ -135,6 +360,16 @@ impl PolyMod {
         self.modules.values()
     }
 
+    /// Collects every struct reachable from this module and its submodules, each one only once.
+    ///
+    /// Registered structs are only walked from when `seed` returns true for them,
+    /// they are still collected when a reachable type refers to them.
+    pub fn reachable_structs(&self, seed: impl Fn(&PolyStruct) -> bool) -> Vec<PolyStruct> {
+        let mut found = Vec::new();
+        collect_module(self, &seed, &mut found, &mut HashSet::new());
+        found
+    }
+
     fn get_target_mod(&mut self, mod_path: impl AsRef<str>) -> &mut PolyMod {
         let mut target_mod = self;
         for mod_name in mod_path.as_ref().split("::").skip(1) {
@@ -148,3 +383,56 @@ impl PolyMod {
         target_mod
     }
 }
+
+/// Json objects can only have string keys, so structs are stored as a list of entries.
+mod struct_entries {
+    use indexmap::IndexMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::ir::{PolyImpl, PolyStruct};
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry<S, I> {
+        #[serde(rename = "struct")]
+        polystruct: S,
+        #[serde(rename = "impl")]
+        polyimpl: I,
+    }
+
+    pub fn serialize<S: Serializer>(
+        structs: &IndexMap<PolyStruct, Option<PolyImpl>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(structs.iter().map(|(s, i)| Entry {
+            polystruct: s,
+            polyimpl: i,
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<IndexMap<PolyStruct, Option<PolyImpl>>, D::Error> {
+        let entries = Vec::<Entry<PolyStruct, Option<PolyImpl>>>::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|e| (e.polystruct, e.polyimpl))
+            .collect())
+    }
+}
+
+/// Registers every item annotated with `#[polygen]` in a module, see [`PolyBag::register_module`].
+///
+/// Paths starting with `crate`, `self` or `super` are resolved relative to the calling module.
+///
+/// ```ignore
+/// let bag = polygen::register_module!(PolyBag::new("Native"), crate::shapes);
+/// ```
+#[macro_export]
+macro_rules! register_module {
+    ($bag:expr, $path:path) => {
+        $bag.register_module(&$crate::__private::resolve_module_path(
+            module_path!(),
+            stringify!($path),
+        ))
+    };
+}
@@ -0,0 +1,520 @@
+//! Compares two [`PolyBag`]s and classifies every change to the exported api.
+//!
+//! Items are matched by their [`ItemKey`](crate::ir::ItemKey),
+//! so every generic instance of a struct is compared separately.
+//! Export names are compared too, existing bindings can not find a function once its name changed.
+
+use std::fmt::Display;
+
+use indexmap::IndexMap;
+use thiserror::Error;
+
+use crate::{
+    ir::{FieldType, FnParams, PolyConst, PolyStruct},
+    PolyBag, PolyMod,
+};
+
+/// How much a change affects existing consumers of the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Existing bindings and code using them keep working.
+    Compatible,
+    /// Existing bindings keep working, but regenerating them breaks code using them.
+    SourceBreaking,
+    /// Existing bindings no longer match the library and have to be regenerated.
+    AbiBreaking,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Compatible => write!(f, "compatible"),
+            Severity::SourceBreaking => write!(f, "source breaking"),
+            Severity::AbiBreaking => write!(f, "abi breaking"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    FunctionAdded,
+    FunctionRemoved,
+    InputAdded {
+        name: String,
+    },
+    InputRemoved {
+        name: String,
+    },
+    InputRenamed {
+        old: String,
+        new: String,
+    },
+    InputTypeChanged {
+        name: String,
+        old: String,
+        new: String,
+    },
+    OutputChanged {
+        old: String,
+        new: String,
+    },
+    TrivialChanged {
+        trivial: bool,
+    },
+    ExportNameChanged {
+        old: String,
+        new: String,
+    },
+    StructAdded,
+    StructRemoved,
+    LayoutChanged {
+        old: (usize, usize),
+        new: (usize, usize),
+    },
+    KindChanged {
+        old: String,
+        new: String,
+    },
+    FieldAdded {
+        name: String,
+    },
+    FieldRemoved {
+        name: String,
+    },
+    FieldsReordered,
+    FieldTypeChanged {
+        name: String,
+        old: String,
+        new: String,
+    },
+    FieldOffsetChanged {
+        name: String,
+        old: usize,
+        new: usize,
+    },
+    FieldVisibilityChanged {
+        name: String,
+        visible: bool,
+    },
+    FieldReadonlyChanged {
+        name: String,
+        readonly: bool,
+    },
+    ConstantAdded,
+    ConstantRemoved,
+    ConstantTypeChanged {
+        old: String,
+        new: String,
+    },
+    ConstantValueChanged {
+        old: String,
+        new: String,
+    },
+}
+
+impl ChangeKind {
+    pub fn severity(&self) -> Severity {
+        use ChangeKind as C;
+        match self {
+            C::FunctionAdded | C::StructAdded | C::ConstantAdded | C::TrivialChanged { .. } => {
+                Severity::Compatible
+            }
+            C::FieldVisibilityChanged { visible, .. } => match visible {
+                true => Severity::Compatible,
+                false => Severity::SourceBreaking,
+            },
+            C::FieldReadonlyChanged { readonly, .. } => match readonly {
+                true => Severity::SourceBreaking,
+                false => Severity::Compatible,
+            },
+            C::InputRenamed { .. } | C::ConstantRemoved | C::ConstantTypeChanged { .. } => {
+                Severity::SourceBreaking
+            }
+            // existing bindings still contain the old value
+            C::ConstantValueChanged { .. } => Severity::AbiBreaking,
+            C::FunctionRemoved
+            | C::InputAdded { .. }
+            | C::InputRemoved { .. }
+            | C::InputTypeChanged { .. }
+            | C::OutputChanged { .. }
+            | C::ExportNameChanged { .. }
+            | C::StructRemoved
+            | C::LayoutChanged { .. }
+            | C::KindChanged { .. }
+            | C::FieldAdded { .. }
+            | C::FieldRemoved { .. }
+            | C::FieldsReordered
+            | C::FieldTypeChanged { .. }
+            | C::FieldOffsetChanged { .. } => Severity::AbiBreaking,
+        }
+    }
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ChangeKind as C;
+        match self {
+            C::FunctionAdded => write!(f, "function added"),
+            C::FunctionRemoved => write!(f, "function removed"),
+            C::InputAdded { name } => write!(f, "input `{name}` added"),
+            C::InputRemoved { name } => write!(f, "input `{name}` removed"),
+            C::InputRenamed { old, new } => write!(f, "input `{old}` renamed to `{new}`"),
+            C::InputTypeChanged { name, old, new } => {
+                write!(f, "input `{name}` changed from `{old}` to `{new}`")
+            }
+            C::OutputChanged { old, new } => write!(f, "output changed from `{old}` to `{new}`"),
+            C::TrivialChanged { trivial } => write!(f, "trivial changed to `{trivial}`"),
+            C::ExportNameChanged { old, new } => {
+                write!(f, "export name changed from `{old}` to `{new}`")
+            }
+            C::StructAdded => write!(f, "struct added"),
+            C::StructRemoved => write!(f, "struct removed"),
+            C::LayoutChanged { old, new } => write!(
+                f,
+                "layout changed from size {} align {} to size {} align {}",
+                old.0, old.1, new.0, new.1
+            ),
+            C::KindChanged { old, new } => write!(f, "kind changed from {old} to {new}"),
+            C::FieldAdded { name } => write!(f, "field `{name}` added"),
+            C::FieldRemoved { name } => write!(f, "field `{name}` removed"),
+            C::FieldsReordered => write!(f, "fields reordered"),
+            C::FieldTypeChanged { name, old, new } => {
+                write!(f, "field `{name}` changed from `{old}` to `{new}`")
+            }
+            C::FieldOffsetChanged { name, old, new } => {
+                write!(f, "field `{name}` moved from offset {old} to {new}")
+            }
+            C::FieldVisibilityChanged { name, visible } => match visible {
+                true => write!(f, "field `{name}` made public"),
+                false => write!(f, "field `{name}` made private"),
+            },
+            C::FieldReadonlyChanged { name, readonly } => match readonly {
+                true => write!(f, "field `{name}` made readonly"),
+                false => write!(f, "field `{name}` made writable"),
+            },
+            C::ConstantAdded => write!(f, "constant added"),
+            C::ConstantRemoved => write!(f, "constant removed"),
+            C::ConstantTypeChanged { old, new } => {
+                write!(f, "constant type changed from `{old}` to `{new}`")
+            }
+            C::ConstantValueChanged { old, new } => {
+                write!(f, "constant value changed from `{old}` to `{new}`")
+            }
+        }
+    }
+}
+
+/// A single change to an item, identified by its path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+impl Change {
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: `{}` {}", self.severity(), self.path, self.kind)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Found {} unexpected change(s):\n{}", .0.len(), display_changes(.0))]
+pub struct UnexpectedChanges(pub Vec<Change>);
+
+fn display_changes(changes: &[Change]) -> String {
+    let changes = changes.iter().map(|c| format!("  {c}"));
+    changes.collect::<Vec<_>>().join("\n")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BagDiff {
+    pub changes: Vec<Change>,
+}
+
+impl BagDiff {
+    /// Compares two bags, where `old` is the previously released api.
+    pub fn new(old: &PolyBag, new: &PolyBag) -> Self {
+        let mut diff = Self::default();
+        let (old_items, new_items) = (Items::collect(old), Items::collect(new));
+
+        for (path, old_fn) in &old_items.functions {
+            match new_items.functions.get(path) {
+                None => diff.push(path, ChangeKind::FunctionRemoved),
+                Some(new_fn) => diff.diff_function(path, old_fn, new_fn),
+            }
+        }
+
+        for path in new_items.functions.keys() {
+            if !old_items.functions.contains_key(path) {
+                diff.push(path, ChangeKind::FunctionAdded);
+            }
+        }
+
+        for (path, old_struct) in &old_items.structs {
+            match new_items.structs.get(path) {
+                None => diff.push(path, ChangeKind::StructRemoved),
+                Some(new_struct) => diff.diff_struct(path, old_struct, new_struct),
+            }
+        }
+
+        for path in new_items.structs.keys() {
+            if !old_items.structs.contains_key(path) {
+                diff.push(path, ChangeKind::StructAdded);
+            }
+        }
+
+        for (path, old_const) in &old_items.constants {
+            match new_items.constants.get(path) {
+                None => diff.push(path, ChangeKind::ConstantRemoved),
+                Some(new_const) => diff.diff_constant(path, old_const, new_const),
+            }
+        }
+
+        for path in new_items.constants.keys() {
+            if !old_items.constants.contains_key(path) {
+                diff.push(path, ChangeKind::ConstantAdded);
+            }
+        }
+
+        diff
+    }
+
+    /// Returns the most severe change, or [`Severity::Compatible`] if nothing changed.
+    pub fn severity(&self) -> Severity {
+        let severities = self.changes.iter().map(Change::severity);
+        severities.max().unwrap_or(Severity::Compatible)
+    }
+
+    /// Fails with every change that is more severe than `allowed`.
+    pub fn check(&self, allowed: Severity) -> Result<(), UnexpectedChanges> {
+        let unexpected = self.changes.iter().filter(|c| c.severity() > allowed);
+        let unexpected = unexpected.cloned().collect::<Vec<_>>();
+        match unexpected.is_empty() {
+            true => Ok(()),
+            false => Err(UnexpectedChanges(unexpected)),
+        }
+    }
+
+    fn push(&mut self, path: &str, kind: ChangeKind) {
+        let path = path.to_string();
+        self.changes.push(Change { path, kind });
+    }
+
+    fn diff_function(&mut self, path: &str, old: &Function, new: &Function) {
+        if old.export_name != new.export_name {
+            let (old, new) = (old.export_name.to_string(), new.export_name.to_string());
+            self.push(path, ChangeKind::ExportNameChanged { old, new });
+        }
+
+        if old.trivial != new.trivial {
+            let trivial = new.trivial;
+            self.push(path, ChangeKind::TrivialChanged { trivial });
+        }
+
+        for (index, old_input) in old.params.inputs.iter().enumerate() {
+            let Some(new_input) = new.params.inputs.get(index) else {
+                let name = old_input.name.clone();
+                self.push(path, ChangeKind::InputRemoved { name });
+                continue;
+            };
+
+            let (old_ty, new_ty) = (
+                old_input.ty.key().to_string(),
+                new_input.ty.key().to_string(),
+            );
+            if old_ty != new_ty {
+                let name = new_input.name.clone();
+                let (old, new) = (old_ty, new_ty);
+                self.push(path, ChangeKind::InputTypeChanged { name, old, new });
+            }
+
+            if old_input.name != new_input.name {
+                let (old, new) = (old_input.name.clone(), new_input.name.clone());
+                self.push(path, ChangeKind::InputRenamed { old, new });
+            }
+        }
+
+        for new_input in new.params.inputs.iter().skip(old.params.inputs.len()) {
+            let name = new_input.name.clone();
+            self.push(path, ChangeKind::InputAdded { name });
+        }
+
+        let old_output = output_name(old.params);
+        let new_output = output_name(new.params);
+        if old_output != new_output {
+            let (old, new) = (old_output, new_output);
+            self.push(path, ChangeKind::OutputChanged { old, new });
+        }
+    }
+
+    fn diff_struct(&mut self, path: &str, old: &PolyStruct, new: &PolyStruct) {
+        if (old.size, old.align) != (new.size, new.align) {
+            let (old, new) = ((old.size, old.align), (new.size, new.align));
+            self.push(path, ChangeKind::LayoutChanged { old, new });
+        }
+
+        let (old_kind, new_kind) = (old.kind.to_string(), new.kind.to_string());
+        if old_kind != new_kind {
+            let (old, new) = (old_kind, new_kind);
+            self.push(path, ChangeKind::KindChanged { old, new });
+        }
+
+        let old_names = old.fields.iter().map(|f| &f.name).collect::<Vec<_>>();
+        let new_names = new.fields.iter().map(|f| &f.name).collect::<Vec<_>>();
+        let mut sorted_old_names = old_names.clone();
+        let mut sorted_new_names = new_names.clone();
+        sorted_old_names.sort();
+        sorted_new_names.sort();
+        let reordered = old_names != new_names && sorted_old_names == sorted_new_names;
+        if reordered {
+            self.push(path, ChangeKind::FieldsReordered);
+        }
+
+        for old_field in &old.fields {
+            let name = old_field.name.clone();
+            let Some(new_field) = new.fields.iter().find(|f| f.name == old_field.name) else {
+                self.push(path, ChangeKind::FieldRemoved { name });
+                continue;
+            };
+
+            let old_ty = field_type_name(old, &old_field.ty);
+            let new_ty = field_type_name(new, &new_field.ty);
+            if old_ty != new_ty {
+                let (old, new) = (old_ty, new_ty);
+                let name = name.clone();
+                self.push(path, ChangeKind::FieldTypeChanged { name, old, new });
+            }
+
+            // offsets always change when fields are reordered
+            if old_field.offset != new_field.offset && !reordered {
+                let (old, new) = (old_field.offset, new_field.offset);
+                let name = name.clone();
+                self.push(path, ChangeKind::FieldOffsetChanged { name, old, new });
+            }
+
+            if old_field.visible != new_field.visible {
+                let visible = new_field.visible;
+                let name = name.clone();
+                self.push(path, ChangeKind::FieldVisibilityChanged { name, visible });
+            }
+
+            if old_field.readonly != new_field.readonly {
+                let readonly = new_field.readonly;
+                self.push(path, ChangeKind::FieldReadonlyChanged { name, readonly });
+            }
+        }
+
+        for new_field in &new.fields {
+            if !old.fields.iter().any(|f| f.name == new_field.name) {
+                let name = new_field.name.clone();
+                self.push(path, ChangeKind::FieldAdded { name });
+            }
+        }
+    }
+
+    fn diff_constant(&mut self, path: &str, old: &PolyConst, new: &PolyConst) {
+        let (old_ty, new_ty) = (old.ty.key().to_string(), new.ty.key().to_string());
+        if old_ty != new_ty {
+            let (old, new) = (old_ty, new_ty);
+            self.push(path, ChangeKind::ConstantTypeChanged { old, new });
+        }
+
+        // compared as text so that a `NaN` constant is equal to itself
+        let (old_value, new_value) = (old.value.to_string(), new.value.to_string());
+        if old_value != new_value {
+            let (old, new) = (old_value, new_value);
+            self.push(path, ChangeKind::ConstantValueChanged { old, new });
+        }
+    }
+}
+
+struct Function<'a> {
+    export_name: &'a str,
+    trivial: bool,
+    params: &'a FnParams,
+}
+
+/// Every function, struct and constant in a bag, keyed by its path.
+struct Items<'a> {
+    functions: IndexMap<String, Function<'a>>,
+    structs: IndexMap<String, &'a PolyStruct>,
+    constants: IndexMap<String, &'a PolyConst>,
+}
+
+impl<'a> Items<'a> {
+    fn collect(bag: &'a PolyBag) -> Self {
+        let mut items = Self {
+            functions: IndexMap::new(),
+            structs: IndexMap::new(),
+            constants: IndexMap::new(),
+        };
+
+        items.collect_module(bag.root_module());
+        items
+    }
+
+    fn collect_module(&mut self, m: &'a PolyMod) {
+        for (s, i) in m.structs() {
+            let struct_path = s.key().to_string();
+            for f in i.iter().flat_map(|i| &i.functions) {
+                let path = format!("{struct_path}::{}", f.name);
+                let (export_name, trivial, params) = (&*f.export_name, f.trivial, &f.params);
+                let function = Function {
+                    export_name,
+                    trivial,
+                    params,
+                };
+                self.functions.insert(path, function);
+            }
+
+            for c in i.iter().flat_map(|i| &i.constants) {
+                self.constants
+                    .insert(format!("{struct_path}::{}", c.name), c);
+            }
+
+            self.structs.insert(struct_path, s);
+        }
+
+        for f in m.functions() {
+            let path = f.key().to_string();
+            let (export_name, trivial, params) = (&*f.export_name, f.trivial, &f.params);
+            let function = Function {
+                export_name,
+                trivial,
+                params,
+            };
+            self.functions.insert(path, function);
+        }
+
+        for c in m.constants() {
+            self.constants.insert(c.key().to_string(), c);
+        }
+
+        for m in m.modules() {
+            self.collect_module(m);
+        }
+    }
+}
+
+fn field_type_name(s: &PolyStruct, ty: &FieldType) -> String {
+    match ty {
+        FieldType::Typed(t) => t.key().to_string(),
+        FieldType::Generic(ident) => match s.generics.iter().find(|g| g.ident == *ident) {
+            Some(generic) => generic.ty.key().to_string(),
+            None => ident.clone(),
+        },
+    }
+}
+
+fn output_name(params: &FnParams) -> String {
+    match &params.output {
+        None => "()".to_string(),
+        Some(t) => t.key().to_string(),
+    }
+}
@@ -0,0 +1,24 @@
+/// Embeds a json dump of every registered item in the compiled library.
+///
+/// The dump can be read from an already built library through the exported
+/// `__polygen_bag_json` function, e.g. by passing the library to the `polygen` cli.
+/// Items marked with `#[polygen(manual)]` are not part of the dump.
+///
+/// ```ignore
+/// polygen::export_bag!("Native");
+/// ```
+#[macro_export]
+macro_rules! export_bag {
+    ($name:expr) => {
+        #[no_mangle]
+        #[doc(hidden)]
+        pub unsafe extern "C" fn __polygen_bag_json(len: *mut usize) -> *const u8 {
+            let json = $crate::__private::embedded_bag_json($name);
+            if !len.is_null() {
+                *len = json.len();
+            }
+
+            json.as_ptr()
+        }
+    };
+}
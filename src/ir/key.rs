@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+use crate::items;
+
+use super::{PolyConst, PolyFn, PolyStruct, PolyType};
+
+/// Uniquely identifies a struct, function or constant across crates.
+///
+/// Two generic instances of the same struct have different keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemKey {
+    pub krate: String,
+    /// The module path inside of the crate, empty for items in the crate root.
+    pub module: String,
+    pub name: String,
+    pub generics: Vec<TypeKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TypeKey {
+    Primitive(String),
+    Struct(ItemKey),
+    Pointer(Box<TypeKey>),
+    FnPtr {
+        inputs: Vec<TypeKey>,
+        output: Option<Box<TypeKey>>,
+    },
+}
+
+impl ItemKey {
+    fn new(module_path: &str, name: &str, generics: Vec<TypeKey>) -> Self {
+        // builtin items use a leading `::` to refer to the polygen crate
+        let module_path = module_path.trim_start_matches("::");
+        let (krate, module) = module_path.split_once("::").unwrap_or((module_path, ""));
+        Self {
+            krate: krate.to_string(),
+            module: module.to_string(),
+            name: name.to_string(),
+            generics,
+        }
+    }
+}
+
+impl Display for ItemKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.krate)?;
+        if !self.module.is_empty() {
+            write!(f, "::{}", self.module)?;
+        }
+
+        write!(f, "::{}", self.name)?;
+        if !self.generics.is_empty() {
+            let generics = self.generics.iter().map(ToString::to_string);
+            write!(f, "<{}>", generics.collect::<Vec<_>>().join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TypeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeKey::Primitive(p) => write!(f, "{p}"),
+            TypeKey::Struct(key) => write!(f, "{key}"),
+            TypeKey::Pointer(t) => write!(f, "*{t}"),
+            TypeKey::FnPtr { inputs, output } => {
+                let inputs = inputs.iter().map(ToString::to_string);
+                write!(
+                    f,
+                    "extern \"C\" fn({})",
+                    inputs.collect::<Vec<_>>().join(", ")
+                )?;
+                match output {
+                    Some(output) => write!(f, " -> {output}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl PolyStruct {
+    pub fn key(&self) -> ItemKey {
+        let generics = self.generics.iter().map(|g| g.ty.key()).collect();
+        ItemKey::new(&self.module, &self.name, generics)
+    }
+}
+
+impl PolyFn {
+    pub fn key(&self) -> ItemKey {
+        ItemKey::new(&self.module, &self.name, Vec::new())
+    }
+}
+
+impl PolyConst {
+    pub fn key(&self) -> ItemKey {
+        ItemKey::new(&self.module, &self.name, Vec::new())
+    }
+}
+
+impl PolyType {
+    pub fn key(&self) -> TypeKey {
+        match self {
+            PolyType::Primitive(p) => TypeKey::Primitive(p.clone()),
+            PolyType::Struct(s) => TypeKey::Struct(s.key()),
+            PolyType::Pointer(t) => TypeKey::Pointer(Box::new(t.key())),
+            PolyType::FnPtr(f) => TypeKey::FnPtr {
+                inputs: f.inputs.iter().map(PolyType::key).collect(),
+                output: f.output.as_ref().map(|t| Box::new(t.key())),
+            },
+        }
+    }
+}
+
+impl items::PolyStruct {
+    pub fn key(&self) -> ItemKey {
+        let generics = self.generics.iter().map(|g| g.ty.key()).collect();
+        ItemKey::new(self.module, self.name, generics)
+    }
+}
+
+impl items::PolyFn {
+    pub fn key(&self) -> ItemKey {
+        ItemKey::new(self.module, self.name, Vec::new())
+    }
+}
+
+impl items::PolyConst {
+    pub fn key(&self) -> ItemKey {
+        ItemKey::new(self.module, self.name, Vec::new())
+    }
+}
+
+impl items::PolyType {
+    pub fn key(&self) -> TypeKey {
+        match self {
+            items::PolyType::Primitive(p) => TypeKey::Primitive(p.to_string()),
+            items::PolyType::Struct(s) => TypeKey::Struct(s.key()),
+            items::PolyType::Pointer(t) => TypeKey::Pointer(Box::new(t().key())),
+            items::PolyType::FnPtr(f) => TypeKey::FnPtr {
+                inputs: f.inputs.iter().map(items::PolyType::key).collect(),
+                output: f.output.map(|t| Box::new(t.key())),
+            },
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! An owned representation of the items in a [`PolyBag`](crate::PolyBag).
+//!
+//! Unlike [`items`](crate::items), which are built at compile time by the `#[polygen]` attribute,
+//! the ir can be serialized and read back in, so generators can run in a separate process.
+
+mod key;
+mod polyconst;
+mod polyfn;
+mod polyimpl;
+mod polystruct;
+
+pub use key::*;
+pub use polyconst::*;
+pub use polyfn::*;
+pub use polyimpl::*;
+pub use polystruct::*;
+
+use thiserror::Error;
+
+/// The version of the json schema written by [`PolyBag::to_json`](crate::PolyBag::to_json).
+///
+/// This is incremented when a release changes the layout of the ir.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum IrError {
+    #[error("Invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported schema version {0}, expected version {SCHEMA_VERSION}")]
+    Version(u32),
+}
@@ -0,0 +1,74 @@
+use std::{fmt::Display, hash::Hash};
+
+use serde::{Deserialize, Serialize};
+
+use super::PolyType;
+use crate::items;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolyConst {
+    pub module: String,
+    pub name: String,
+    pub docs: String,
+    pub ty: PolyType,
+    pub value: PolyValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PolyValue {
+    #[serde(rename = "bool")]
+    Bool(bool),
+    #[serde(rename = "int")]
+    Int(i64),
+    #[serde(rename = "uint")]
+    UInt(u64),
+    #[serde(rename = "float")]
+    Float(f64),
+}
+
+impl Eq for PolyConst {}
+impl PartialEq for PolyConst {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Hash for PolyConst {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl Display for PolyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolyValue::Bool(v) => write!(f, "{v}"),
+            PolyValue::Int(v) => write!(f, "{v}"),
+            PolyValue::UInt(v) => write!(f, "{v}"),
+            PolyValue::Float(v) => write!(f, "{v:?}"),
+        }
+    }
+}
+
+impl From<&items::PolyConst> for PolyConst {
+    fn from(value: &items::PolyConst) -> Self {
+        Self {
+            module: value.module.to_string(),
+            name: value.name.to_string(),
+            docs: value.docs.to_string(),
+            ty: (&value.ty).into(),
+            value: (value.value)().into(),
+        }
+    }
+}
+
+impl From<items::PolyValue> for PolyValue {
+    fn from(value: items::PolyValue) -> Self {
+        match value {
+            items::PolyValue::Bool(v) => Self::Bool(v),
+            items::PolyValue::Int(v) => Self::Int(v),
+            items::PolyValue::UInt(v) => Self::UInt(v),
+            items::PolyValue::Float(v) => Self::Float(v),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+
+use super::PolyType;
+use crate::items;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolyFn {
+    pub module: String,
+    pub name: String,
+    pub docs: String,
+    pub export_name: String,
+    pub trivial: bool,
+    pub params: FnParams,
+}
+
+impl Eq for PolyFn {}
+impl PartialEq for PolyFn {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Hash for PolyFn {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FnParams {
+    pub inputs: Vec<FnInput>,
+    pub output: Option<PolyType>,
+}
+
+/// A method of a trait that is implemented by foreign code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraitFn {
+    pub name: String,
+    pub docs: String,
+    /// The inputs of the method without its receiver.
+    pub params: FnParams,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FnInput {
+    pub name: String,
+    pub docs: String,
+    pub ty: PolyType,
+    /// The native value is moved out of the handle passed as this input.
+    pub consumed: bool,
+}
+
+impl From<&items::PolyFn> for PolyFn {
+    fn from(value: &items::PolyFn) -> Self {
+        Self {
+            module: value.module.to_string(),
+            name: value.name.to_string(),
+            docs: value.docs.to_string(),
+            export_name: value.export_name.to_string(),
+            trivial: value.trivial,
+            params: (&value.params).into(),
+        }
+    }
+}
+
+impl From<&items::FnParams> for FnParams {
+    fn from(value: &items::FnParams) -> Self {
+        Self {
+            inputs: value.inputs.iter().map(Into::into).collect(),
+            output: value.output.as_ref().map(Into::into),
+        }
+    }
+}
+
+impl From<&items::FnInput> for FnInput {
+    fn from(value: &items::FnInput) -> Self {
+        Self {
+            name: value.name.to_string(),
+            docs: value.docs.to_string(),
+            ty: value.ty.into(),
+            consumed: value.consumed,
+        }
+    }
+}
+
+impl From<&items::TraitFn> for TraitFn {
+    fn from(value: &items::TraitFn) -> Self {
+        Self {
+            name: value.name.to_string(),
+            docs: value.docs.to_string(),
+            params: (&value.params).into(),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use super::{FnParams, PolyConst};
+use crate::items;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolyImpl {
+    pub functions: Vec<ImplFn>,
+    pub constants: Vec<PolyConst>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImplFn {
+    pub name: String,
+    pub docs: String,
+    pub export_name: String,
+    pub trivial: bool,
+    pub params: FnParams,
+}
+
+impl From<&items::PolyImpl> for PolyImpl {
+    fn from(value: &items::PolyImpl) -> Self {
+        Self {
+            functions: value.functions.iter().map(Into::into).collect(),
+            constants: value.constants.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&items::ImplFn> for ImplFn {
+    fn from(value: &items::ImplFn) -> Self {
+        Self {
+            name: value.name.to_string(),
+            docs: value.docs.to_string(),
+            export_name: value.export_name.to_string(),
+            trivial: value.trivial,
+            params: (&value.params).into(),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::{fmt::Display, hash::Hash};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ItemKey, TraitFn};
+use crate::items;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolyStruct {
+    pub module: String,
+    pub name: String,
+    pub docs: String,
+    pub size: usize,
+    pub align: usize,
+    pub fields: Vec<StructField>,
+    pub generics: Vec<PolyGeneric>,
+    pub kind: StructKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StructKind {
+    /// A struct whose fields are read and written by foreign code.
+    #[serde(rename = "data")]
+    Data,
+    /// The vtable of a trait, filled by foreign code to implement the trait.
+    #[serde(rename = "vtable")]
+    VTable(Vec<TraitFn>),
+    /// A boxed value owned by the native library and released through `__polygen_drop`.
+    ///
+    /// Trait objects carry the vtable struct of their trait, whose interface they implement.
+    #[serde(rename = "handle")]
+    Handle(Option<Box<PolyType>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PolyType {
+    #[serde(rename = "primitive")]
+    Primitive(String),
+    #[serde(rename = "struct")]
+    Struct(PolyStruct),
+    #[serde(rename = "pointer")]
+    Pointer(Box<PolyType>),
+    #[serde(rename = "fn_ptr")]
+    FnPtr(PolyFnPtr),
+}
+
+/// The signature of an `extern "C"` function pointer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PolyFnPtr {
+    pub inputs: Vec<PolyType>,
+    pub output: Option<Box<PolyType>>,
+}
+
+impl PolyStruct {
+    /// Resolves the type of a field, replacing a generic with the type it is instantiated with.
+    pub fn resolve_field<'a>(&'a self, ty: &'a FieldType) -> &'a PolyType {
+        match ty {
+            FieldType::Typed(t) => t,
+            FieldType::Generic(ident) => match self.generics.iter().find(|g| g.ident == *ident) {
+                Some(generic) => &generic.ty,
+                None => panic!("generic `{ident}` is not defined on struct `{}`", self.name),
+            },
+        }
+    }
+}
+
+impl Display for StructKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StructKind::Data => write!(f, "data"),
+            StructKind::VTable(_) => write!(f, "vtable"),
+            StructKind::Handle(_) => write!(f, "handle"),
+        }
+    }
+}
+
+impl Eq for PolyStruct {}
+impl PartialEq for PolyStruct {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Hash for PolyStruct {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructField {
+    pub visible: bool,
+    pub readonly: bool,
+    pub name: String,
+    pub docs: String,
+    pub offset: usize,
+    pub ty: FieldType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FieldType {
+    #[serde(rename = "generic")]
+    Generic(String),
+    #[serde(rename = "typed")]
+    Typed(PolyType),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolyGeneric {
+    pub ident: String,
+    pub ty: PolyType,
+}
+
+impl From<&items::PolyStruct> for PolyStruct {
+    fn from(value: &items::PolyStruct) -> Self {
+        convert_struct(value, &mut Vec::new())
+    }
+}
+
+impl From<&items::PolyType> for PolyType {
+    fn from(value: &items::PolyType) -> Self {
+        convert_type(value, &mut Vec::new())
+    }
+}
+
+impl From<&items::StructField> for StructField {
+    fn from(value: &items::StructField) -> Self {
+        convert_field(value, &mut Vec::new())
+    }
+}
+
+impl From<&items::PolyGeneric> for PolyGeneric {
+    fn from(value: &items::PolyGeneric) -> Self {
+        convert_generic(value, &mut Vec::new())
+    }
+}
+
+/// Converts a struct, where `stack` holds every struct the conversion is currently inside of.
+fn convert_struct(value: &items::PolyStruct, stack: &mut Vec<ItemKey>) -> PolyStruct {
+    let generics = value.generics.iter().map(|g| convert_generic(g, stack));
+
+    let mut polystruct = PolyStruct {
+        module: value.module.to_string(),
+        name: value.name.to_string(),
+        docs: value.docs.to_string(),
+        size: value.size,
+        align: value.align,
+        fields: Vec::new(),
+        generics: generics.collect(),
+        kind: match value.kind {
+            items::StructKind::Data => StructKind::Data,
+            items::StructKind::VTable(methods) => {
+                StructKind::VTable(methods.iter().map(Into::into).collect())
+            }
+            items::StructKind::Handle(interface) => {
+                StructKind::Handle(interface.map(|t| Box::new(convert_type(t, stack))))
+            }
+        },
+    };
+
+    // a struct can only contain itself through a pointer, which only needs its name
+    let key = value.key();
+    if stack.contains(&key) {
+        return polystruct;
+    }
+
+    stack.push(key);
+    let fields = value.fields.iter().map(|f| convert_field(f, stack));
+    polystruct.fields = fields.collect();
+    stack.pop();
+    polystruct
+}
+
+fn convert_type(value: &items::PolyType, stack: &mut Vec<ItemKey>) -> PolyType {
+    match value {
+        items::PolyType::Primitive(p) => PolyType::Primitive(p.to_string()),
+        items::PolyType::Struct(s) => PolyType::Struct(convert_struct(s, stack)),
+        items::PolyType::Pointer(t) => PolyType::Pointer(Box::new(convert_type(&t(), stack))),
+        items::PolyType::FnPtr(f) => PolyType::FnPtr(PolyFnPtr {
+            inputs: f.inputs.iter().map(|t| convert_type(t, stack)).collect(),
+            output: f.output.map(|t| Box::new(convert_type(t, stack))),
+        }),
+    }
+}
+
+fn convert_field(value: &items::StructField, stack: &mut Vec<ItemKey>) -> StructField {
+    StructField {
+        visible: value.visible,
+        readonly: value.readonly,
+        name: value.name.to_string(),
+        docs: value.docs.to_string(),
+        offset: value.offset,
+        ty: match value.ty {
+            items::FieldType::Generic(ident) => FieldType::Generic(ident.to_string()),
+            items::FieldType::Typed(t) => FieldType::Typed(convert_type(t, stack)),
+        },
+    }
+}
+
+fn convert_generic(value: &items::PolyGeneric, stack: &mut Vec<ItemKey>) -> PolyGeneric {
+    PolyGeneric {
+        ident: value.ident.to_string(),
+        ty: convert_type(value.ty, stack),
+    }
+}
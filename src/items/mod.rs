@@ -1,9 +1,11 @@
+mod polyconst;
 mod polyfn;
 mod polyimpl;
 mod polystruct;
 
 pub mod types;
 
+pub use polyconst::*;
 pub use polyfn::*;
 pub use polyimpl::*;
 pub use polystruct::*;
@@ -0,0 +1,44 @@
+use std::hash::Hash;
+
+use serde::{Serialize, Serializer};
+
+use super::PolyType;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PolyConst {
+    pub module: &'static str,
+    pub name: &'static str,
+    pub docs: &'static str,
+    pub ty: PolyType,
+    #[serde(serialize_with = "serialize_value")]
+    pub value: fn() -> PolyValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum PolyValue {
+    #[serde(rename = "bool")]
+    Bool(bool),
+    #[serde(rename = "int")]
+    Int(i64),
+    #[serde(rename = "uint")]
+    UInt(u64),
+    #[serde(rename = "float")]
+    Float(f64),
+}
+
+fn serialize_value<S: Serializer>(value: &fn() -> PolyValue, s: S) -> Result<S::Ok, S::Error> {
+    value().serialize(s)
+}
+
+impl Eq for PolyConst {}
+impl PartialEq for PolyConst {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Hash for PolyConst {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
@@ -8,21 +8,22 @@ use super::PolyType;
 pub struct PolyFn {
     pub module: &'static str,
     pub name: &'static str,
+    pub docs: &'static str,
     pub export_name: &'static str,
+    pub trivial: bool,
     pub params: FnParams,
 }
 
 impl Eq for PolyFn {}
 impl PartialEq for PolyFn {
     fn eq(&self, other: &Self) -> bool {
-        self.module == self.module && self.name == other.name
+        self.key() == other.key()
     }
 }
 
 impl Hash for PolyFn {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.module.hash(state);
-        self.name.hash(state);
+        self.key().hash(state);
     }
 }
 
@@ -35,5 +36,17 @@ pub struct FnParams {
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct FnInput {
     pub name: &'static str,
+    pub docs: &'static str,
     pub ty: &'static PolyType,
+    /// The native value is moved out of the handle passed as this input.
+    pub consumed: bool,
+}
+
+/// A method of a trait that is implemented by foreign code.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TraitFn {
+    pub name: &'static str,
+    pub docs: &'static str,
+    /// The inputs of the method without its receiver.
+    pub params: FnParams,
 }
@@ -1,15 +1,18 @@
 use serde::Serialize;
 
-use super::FnParams;
+use super::{FnParams, PolyConst};
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct PolyImpl {
     pub functions: &'static [ImplFn],
+    pub constants: &'static [PolyConst],
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct ImplFn {
     pub name: &'static str,
+    pub docs: &'static str,
     pub export_name: &'static str,
+    pub trivial: bool,
     pub params: FnParams,
 }
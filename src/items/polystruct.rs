@@ -1,13 +1,35 @@
 use std::hash::Hash;
 
-use serde::Serialize;
+use serde::{Serialize, Serializer};
+
+use super::TraitFn;
+use crate::ir;
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct PolyStruct {
     pub module: &'static str,
     pub name: &'static str,
+    pub docs: &'static str,
+    pub size: usize,
+    pub align: usize,
     pub fields: &'static [StructField],
     pub generics: &'static [PolyGeneric],
+    pub kind: StructKind,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum StructKind {
+    /// A struct whose fields are read and written by foreign code.
+    #[serde(rename = "data")]
+    Data,
+    /// The vtable of a trait, filled by foreign code to implement the trait.
+    #[serde(rename = "vtable")]
+    VTable(&'static [TraitFn]),
+    /// A boxed value owned by the native library and released through `__polygen_drop`.
+    ///
+    /// Trait objects carry the vtable struct of their trait, whose interface they implement.
+    #[serde(rename = "handle")]
+    Handle(Option<&'static PolyType>),
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
@@ -16,28 +38,44 @@ pub enum PolyType {
     Primitive(&'static str),
     #[serde(rename = "struct")]
     Struct(PolyStruct),
-    #[serde(rename = "pointer")]
-    Pointer(&'static PolyType),
+    #[serde(rename = "pointer", serialize_with = "serialize_pointee")]
+    Pointer(fn() -> PolyType),
+    #[serde(rename = "fn_ptr")]
+    FnPtr(PolyFnPtr),
+}
+
+/// The signature of an `extern "C"` function pointer.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PolyFnPtr {
+    pub inputs: &'static [PolyType],
+    pub output: Option<&'static PolyType>,
+}
+
+/// Serializes the owned form of the pointee, which stops at recursive structs.
+fn serialize_pointee<S: Serializer>(pointee: &fn() -> PolyType, s: S) -> Result<S::Ok, S::Error> {
+    ir::PolyType::from(&pointee()).serialize(s)
 }
 
 impl Eq for PolyStruct {}
 impl PartialEq for PolyStruct {
     fn eq(&self, other: &Self) -> bool {
-        self.module == self.module && self.name == other.name
+        self.key() == other.key()
     }
 }
 
 impl Hash for PolyStruct {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.module.hash(state);
-        self.name.hash(state);
+        self.key().hash(state);
     }
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct StructField {
     pub visible: bool,
+    pub readonly: bool,
     pub name: &'static str,
+    pub docs: &'static str,
+    pub offset: usize,
     pub ty: FieldType,
 }
 
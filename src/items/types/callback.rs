@@ -0,0 +1,121 @@
+use std::{
+    ffi::c_void,
+    mem::{align_of, offset_of, size_of},
+};
+
+use crate::{
+    __private::ExportedPolyStruct,
+    items::{FieldType, PolyGeneric, PolyStruct, PolyType, StructField, StructKind},
+};
+
+/// The argument tuples a [`PolyCallback`] returning `Ret` can be called with.
+pub trait CallbackArgs<Ret>: Sized + 'static {
+    /// The foreign function, which receives the user data as its last argument.
+    type Fn: ExportedPolyStruct<ExportedType = Self::Fn> + Copy;
+
+    fn call(func: Self::Fn, args: Self, user_data: *mut c_void) -> Ret;
+}
+
+macro_rules! impl_callback_args {
+    ($($arg:ident $name:ident),*) => {
+        impl<$($arg: 'static,)* Ret: 'static> CallbackArgs<Ret> for ($($arg,)*)
+        where
+            extern "C" fn($($arg,)* *mut c_void) -> Ret:
+                ExportedPolyStruct<ExportedType = extern "C" fn($($arg,)* *mut c_void) -> Ret>,
+        {
+            type Fn = extern "C" fn($($arg,)* *mut c_void) -> Ret;
+
+            fn call(func: Self::Fn, ($($name,)*): Self, user_data: *mut c_void) -> Ret {
+                func($($name,)* user_data)
+            }
+        }
+    };
+}
+
+impl_callback_args!();
+impl_callback_args!(A a);
+impl_callback_args!(A a, B b);
+impl_callback_args!(A a, B b, C c);
+impl_callback_args!(A a, B b, C c, D d);
+impl_callback_args!(A a, B b, C c, D d, E e);
+impl_callback_args!(A a, B b, C c, D d, E e, F f);
+
+/// A foreign function bundled with the user data it is called with.
+///
+/// The user data is passed as the last argument of every call.
+/// Once the callback is dropped its optional destructor is called with the user data,
+/// which lets foreign code release whatever kept the function alive.
+#[repr(C)]
+pub struct PolyCallback<Args: CallbackArgs<Ret>, Ret = ()> {
+    func: Args::Fn,
+    user_data: *mut c_void,
+    drop: Option<extern "C" fn(*mut c_void)>,
+}
+
+impl<Args: CallbackArgs<Ret>, Ret> PolyCallback<Args, Ret> {
+    pub fn new(
+        func: Args::Fn,
+        user_data: *mut c_void,
+        drop: Option<extern "C" fn(*mut c_void)>,
+    ) -> Self {
+        Self {
+            func,
+            user_data,
+            drop,
+        }
+    }
+
+    pub fn call(&self, args: Args) -> Ret {
+        Args::call(self.func, args, self.user_data)
+    }
+}
+
+impl<Args: CallbackArgs<Ret>, Ret> Drop for PolyCallback<Args, Ret> {
+    fn drop(&mut self) {
+        if let Some(drop) = self.drop {
+            drop(self.user_data);
+        }
+    }
+}
+
+unsafe impl<Args: CallbackArgs<Ret>, Ret: 'static> ExportedPolyStruct for PolyCallback<Args, Ret> {
+    type ExportedType = Self;
+    const STRUCT: PolyType = PolyType::Struct(PolyStruct {
+        module: "::polygen",
+        name: "PolyCallback",
+        docs: "A foreign function and the user data it is called with.",
+        size: size_of::<Self>(),
+        align: align_of::<Self>(),
+        fields: &[
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "func",
+                docs: "",
+                offset: offset_of!(Self, func),
+                ty: FieldType::Generic("F"),
+            },
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "user_data",
+                docs: "",
+                offset: offset_of!(Self, user_data),
+                ty: FieldType::Typed(&<*mut c_void as ExportedPolyStruct>::STRUCT),
+            },
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "drop",
+                docs: "",
+                offset: offset_of!(Self, drop),
+                ty: FieldType::Typed(&<extern "C" fn(*mut c_void) as ExportedPolyStruct>::STRUCT),
+            },
+        ],
+        generics: &[PolyGeneric {
+            ident: "F",
+            ty: &<Args::Fn as ExportedPolyStruct>::STRUCT,
+        }],
+        kind: StructKind::Data,
+    });
+}
@@ -0,0 +1,23 @@
+use std::ffi::c_void;
+
+/// Destructor exported for foreign code to release boxed values.
+///
+/// Boxed types like [`PolyBox`](super::PolyBox), [`PolyHandle`](super::PolyHandle),
+/// [`OpaquePtr`](super::OpaquePtr) and [`DynHandle`](super::DynHandle)
+/// carry a pointer to the destructor for their concrete type,
+/// which is passed here together with the pointer to release.
+#[no_mangle]
+#[doc(hidden)]
+pub unsafe extern "C" fn __polygen_drop(
+    ptr: *mut c_void,
+    destructor: unsafe extern "C" fn(*mut c_void),
+) {
+    if !ptr.is_null() {
+        destructor(ptr);
+    }
+}
+
+/// Drops a value that was allocated using a [`Box`].
+pub(super) unsafe extern "C" fn drop_box<T>(ptr: *mut T) {
+    drop(Box::from_raw(ptr));
+}
@@ -0,0 +1,43 @@
+use crate::{
+    __private::ExportedPolyStruct,
+    items::{PolyFnPtr, PolyType},
+};
+
+// function pointers are called by foreign code directly,
+// so every argument and the return value must already be FFI safe
+macro_rules! impl_fn_ptr {
+    ($($arg:ident),*) => {
+        unsafe impl<$($arg,)* R> ExportedPolyStruct for extern "C" fn($($arg),*) -> R
+        where
+            $($arg: ExportedPolyStruct<ExportedType = $arg>,)*
+            R: ExportedPolyStruct<ExportedType = R>,
+        {
+            type ExportedType = Self;
+            const STRUCT: PolyType = PolyType::FnPtr(PolyFnPtr {
+                inputs: &[$(<$arg as ExportedPolyStruct>::STRUCT),*],
+                output: Some(&<R as ExportedPolyStruct>::STRUCT),
+            });
+        }
+
+        unsafe impl<$($arg),*> ExportedPolyStruct for extern "C" fn($($arg),*)
+        where
+            $($arg: ExportedPolyStruct<ExportedType = $arg>,)*
+        {
+            type ExportedType = Self;
+            const STRUCT: PolyType = PolyType::FnPtr(PolyFnPtr {
+                inputs: &[$(<$arg as ExportedPolyStruct>::STRUCT),*],
+                output: None,
+            });
+        }
+    };
+}
+
+impl_fn_ptr!();
+impl_fn_ptr!(A);
+impl_fn_ptr!(A, B);
+impl_fn_ptr!(A, B, C);
+impl_fn_ptr!(A, B, C, D);
+impl_fn_ptr!(A, B, C, D, E);
+impl_fn_ptr!(A, B, C, D, E, F);
+impl_fn_ptr!(A, B, C, D, E, F, G);
+impl_fn_ptr!(A, B, C, D, E, F, G, H);
@@ -0,0 +1,120 @@
+use std::{
+    ffi::c_void,
+    mem::{align_of, offset_of, size_of},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    __private::{ExportedPolyStruct, PolyReceiver},
+    items::{FieldType, PolyStruct, PolyType, StructField, StructKind},
+};
+
+use super::drop::drop_box;
+
+/// A heap allocated value of a type that is not `#[repr(C)]`.
+///
+/// `T` is exported by implementing [`ExportedPolyStruct`] with `PolyHandle<T>`
+/// as its exported type, using [`PolyHandle::describe`] for its struct
+/// and setting `CONSUMES_HANDLE` when `From<PolyHandle<T>>` moves the value out of the handle.
+/// Foreign code gets a distinct class for every `T` with the methods of its `#[polygen]` impl blocks,
+/// and releases the value through the exported `__polygen_drop` function.
+/// Dropping the handle in rust does not free the value.
+#[repr(C)]
+pub struct PolyHandle<T: 'static> {
+    ptr: *mut Option<T>,
+    drop: unsafe extern "C" fn(*mut Option<T>),
+}
+
+impl<T: 'static> PolyHandle<T> {
+    const FIELDS: &'static [StructField] = &[
+        StructField {
+            visible: false,
+            readonly: false,
+            name: "ptr",
+            docs: "",
+            offset: offset_of!(Self, ptr),
+            ty: FieldType::Typed(&<usize as ExportedPolyStruct>::STRUCT),
+        },
+        StructField {
+            visible: false,
+            readonly: false,
+            name: "drop",
+            docs: "",
+            offset: offset_of!(Self, drop),
+            ty: FieldType::Typed(&<extern "C" fn(*mut c_void) as ExportedPolyStruct>::STRUCT),
+        },
+    ];
+
+    pub fn new(item: T) -> Self {
+        Self {
+            ptr: Box::into_raw(Box::new(Some(item))),
+            drop: drop_box::<Option<T>>,
+        }
+    }
+
+    /// Describes the foreign class of the handle, to be used as the struct of `T`.
+    pub const fn describe(
+        module: &'static str,
+        name: &'static str,
+        docs: &'static str,
+    ) -> PolyType {
+        PolyType::Struct(PolyStruct {
+            module,
+            name,
+            docs,
+            size: size_of::<Self>(),
+            align: align_of::<Self>(),
+            fields: Self::FIELDS,
+            generics: &[],
+            kind: StructKind::Handle(None),
+        })
+    }
+
+    /// Moves the value out of the handle.
+    ///
+    /// Foreign code still owns the handle, so any later access through it panics.
+    pub fn into_inner(self) -> T {
+        unsafe { &mut *self.ptr }
+            .take()
+            .expect("The value was moved out of its handle")
+    }
+}
+
+impl<T: 'static> Deref for PolyHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+            .as_ref()
+            .expect("The value was moved out of its handle")
+    }
+}
+
+impl<T: 'static> DerefMut for PolyHandle<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.ptr }
+            .as_mut()
+            .expect("The value was moved out of its handle")
+    }
+}
+
+impl<T: 'static> From<T> for PolyHandle<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ExportedPolyStruct<ExportedType = PolyHandle<T>>> PolyReceiver<T> for PolyHandle<T> {
+    type Receiver = PolyHandle<T>;
+
+    fn receiver(receiver: &mut Self::Receiver) -> &mut T {
+        receiver
+    }
+}
+
+unsafe impl<T: ExportedPolyStruct<ExportedType = PolyHandle<T>>> ExportedPolyStruct
+    for PolyHandle<T>
+{
+    type ExportedType = PolyHandle<T>;
+    const STRUCT: PolyType = T::STRUCT;
+}
@@ -1,13 +1,19 @@
+mod callback;
+mod drop;
+mod fnptr;
+mod handle;
 mod opaque;
 mod option;
 mod polybox;
+mod polydyn;
 mod primitives;
 mod ptr;
-mod typeid;
 
+pub use callback::*;
+pub use handle::*;
 pub use opaque::*;
 pub use option::*;
 pub use polybox::*;
+pub use polydyn::*;
 pub use primitives::*;
 pub use ptr::*;
-pub use typeid::*;
@@ -1,47 +1,68 @@
-use std::any::TypeId;
+use std::{
+    any::TypeId,
+    ffi::c_void,
+    hash::{Hash, Hasher},
+    mem::{align_of, offset_of, size_of},
+};
 
 use thiserror::Error;
 
 use crate::{
     __private::ExportedPolyStruct,
-    items::{FieldType, PolyStruct, PolyType, StructField},
+    items::{FieldType, PolyStruct, PolyType, StructField, StructKind},
 };
 
 #[derive(Debug, Error)]
 #[error("Pointer is invalid")]
 pub struct InvalidPtr;
 
+/// A type erased heap allocated value that can be passed to foreign code.
+///
+/// Like [`PolyBox`](super::PolyBox) the pointer carries its own destructor
+/// so foreign code can release it through the exported `__polygen_drop` function.
 #[repr(C)]
 pub struct OpaquePtr {
-    id: TypeId,
+    id: u64,
     ptr: usize,
+    drop: unsafe extern "C" fn(*mut c_void),
 }
 
 impl OpaquePtr {
     pub fn new<T: 'static>(item: T) -> Self {
         Self {
-            id: TypeId::of::<T>(),
-            ptr: Box::into_raw(Box::new(item)) as usize,
+            id: type_hash::<T>(),
+            ptr: Box::into_raw(Box::new(Some(item))) as usize,
+            drop: drop_opaque::<T>,
         }
     }
 
     pub fn as_ref<T: 'static>(&self) -> Result<&T, InvalidPtr> {
         self.validate_pointer::<T>()?;
-        Ok(unsafe { &*(self.ptr as *const T) })
+        unsafe { &*(self.ptr as *const Option<T>) }
+            .as_ref()
+            .ok_or(InvalidPtr)
     }
 
     pub fn as_mut<T: 'static>(&mut self) -> Result<&mut T, InvalidPtr> {
         self.validate_pointer::<T>()?;
-        Ok(unsafe { &mut *(self.ptr as *mut T) })
+        unsafe { &mut *(self.ptr as *mut Option<T>) }
+            .as_mut()
+            .ok_or(InvalidPtr)
     }
 
+    /// Moves the value out of the pointer.
+    ///
+    /// Foreign code still owns the allocation and releases it as usual,
+    /// any later access through the pointer returns [`InvalidPtr`].
     pub fn into_inner<T: 'static>(self) -> Result<T, InvalidPtr> {
         self.validate_pointer::<T>()?;
-        Ok(unsafe { *Box::from_raw(self.ptr as *mut T) })
+        unsafe { &mut *(self.ptr as *mut Option<T>) }
+            .take()
+            .ok_or(InvalidPtr)
     }
 
     fn validate_pointer<T: 'static>(&self) -> Result<(), InvalidPtr> {
-        if self.id != TypeId::of::<T>() {
+        if self.id != type_hash::<T>() {
             return Err(InvalidPtr);
         }
 
@@ -49,23 +70,53 @@ impl OpaquePtr {
     }
 }
 
+// the layout of TypeId is unspecified and differs between compiler versions,
+// so only a 64 bit hash of it is stored, which just has to match within one build
+fn type_hash<T: 'static>() -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    TypeId::of::<T>().hash(&mut hasher);
+    hasher.finish()
+}
+
+unsafe extern "C" fn drop_opaque<T>(ptr: *mut c_void) {
+    drop(Box::from_raw(ptr as *mut Option<T>));
+}
+
 unsafe impl ExportedPolyStruct for OpaquePtr {
     type ExportedType = OpaquePtr;
     const STRUCT: PolyType = PolyType::Struct(PolyStruct {
         module: "::polygen",
         name: stringify!(OpaquePtr),
+        docs: "A pointer to a native value that can not be accessed from foreign code.",
+        size: size_of::<Self>(),
+        align: align_of::<Self>(),
         fields: &[
             StructField {
                 visible: false,
+                readonly: false,
                 name: "id",
-                ty: FieldType::Typed(&<TypeId as ExportedPolyStruct>::STRUCT),
+                docs: "",
+                offset: offset_of!(Self, id),
+                ty: FieldType::Typed(&<u64 as ExportedPolyStruct>::STRUCT),
             },
             StructField {
                 visible: false,
+                readonly: false,
                 name: "ptr",
+                docs: "",
+                offset: offset_of!(Self, ptr),
                 ty: FieldType::Typed(&<usize as ExportedPolyStruct>::STRUCT),
             },
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "drop",
+                docs: "",
+                offset: offset_of!(Self, drop),
+                ty: FieldType::Typed(&<extern "C" fn(*mut c_void) as ExportedPolyStruct>::STRUCT),
+            },
         ],
         generics: &[],
+        kind: StructKind::Handle(None),
     });
 }
@@ -1,8 +1,8 @@
-use std::mem::MaybeUninit;
+use std::mem::{align_of, offset_of, size_of, MaybeUninit};
 
 use crate::{
     __private::ExportedPolyStruct,
-    items::{FieldType, PolyGeneric, PolyStruct, PolyType, StructField},
+    items::{FieldType, PolyGeneric, PolyStruct, PolyType, StructField, StructKind},
 };
 
 #[repr(C)]
@@ -32,14 +32,31 @@ unsafe impl<T: ExportedPolyStruct> ExportedPolyStruct for PolyOption<T> {
     const STRUCT: PolyType = PolyType::Struct(PolyStruct {
         module: "::polygen",
         name: "PolyOption",
-        fields: &[StructField {
-            visible: false,
-            name: "valid",
-            ty: FieldType::Generic("T"),
-        }],
+        docs: "An optional value, where `data` is only initialized if `valid` is set.",
+        size: size_of::<Self>(),
+        align: align_of::<Self>(),
+        fields: &[
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "valid",
+                docs: "",
+                offset: offset_of!(Self, valid),
+                ty: FieldType::Typed(&<bool as ExportedPolyStruct>::STRUCT),
+            },
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "data",
+                docs: "",
+                offset: offset_of!(Self, data),
+                ty: FieldType::Generic("T"),
+            },
+        ],
         generics: &[PolyGeneric {
             ident: "T",
             ty: &T::STRUCT,
         }],
+        kind: StructKind::Data,
     });
 }
@@ -1,13 +1,25 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    ffi::c_void,
+    mem::{align_of, offset_of, size_of},
+    ops::{Deref, DerefMut},
+};
 
 use crate::{
     __private::ExportedPolyStruct,
-    items::{PolyGeneric, PolyStruct, PolyType, StructField},
+    items::{FieldType, PolyGeneric, PolyStruct, PolyType, StructField, StructKind},
 };
 
+use super::drop::drop_box;
+
+/// A heap allocated value that can be passed to foreign code.
+///
+/// The box carries the destructor for `T` so foreign code can release it
+/// through the exported `__polygen_drop` function once it is no longer used.
+/// Dropping the box in rust does not free the value.
 #[repr(C)]
 pub struct PolyBox<T: ExportedPolyStruct> {
     ptr: *mut T,
+    drop: unsafe extern "C" fn(*mut T),
 }
 
 impl<T: ExportedPolyStruct> PolyBox<T> {
@@ -34,6 +46,7 @@ impl<T: ExportedPolyStruct> From<Box<T>> for PolyBox<T> {
     fn from(value: Box<T>) -> Self {
         Self {
             ptr: Box::into_raw(value),
+            drop: drop_box::<T>,
         }
     }
 }
@@ -43,14 +56,31 @@ unsafe impl<T: ExportedPolyStruct> ExportedPolyStruct for PolyBox<T> {
     const STRUCT: PolyType = PolyType::Struct(PolyStruct {
         module: "::polygen",
         name: "PolyBox",
-        fields: &[StructField {
-            visible: false,
-            name: "ptr",
-            ty: crate::items::FieldType::Typed(&<usize as ExportedPolyStruct>::STRUCT),
-        }],
+        docs: "A pointer to a boxed value that is dropped by the native library.",
+        size: size_of::<Self>(),
+        align: align_of::<Self>(),
+        fields: &[
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "ptr",
+                docs: "",
+                offset: offset_of!(Self, ptr),
+                ty: FieldType::Typed(&<usize as ExportedPolyStruct>::STRUCT),
+            },
+            StructField {
+                visible: false,
+                readonly: false,
+                name: "drop",
+                docs: "",
+                offset: offset_of!(Self, drop),
+                ty: FieldType::Typed(&<extern "C" fn(*mut c_void) as ExportedPolyStruct>::STRUCT),
+            },
+        ],
         generics: &[PolyGeneric {
             ident: "T",
             ty: &<T as ExportedPolyStruct>::STRUCT,
         }],
+        kind: StructKind::Handle(None),
     });
 }
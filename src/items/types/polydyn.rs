@@ -0,0 +1,146 @@
+use std::{
+    ffi::c_void,
+    mem::{offset_of, replace},
+    sync::Arc,
+};
+
+use crate::{
+    __private::{ExportedPolyDyn, ExportedPolyStruct},
+    items::{FieldType, PolyType, StructField},
+};
+
+use super::drop::drop_box;
+
+/// The trait object behind a [`DynHandle`].
+enum DynObject<T: ?Sized> {
+    Boxed(Box<T>),
+    Shared(Arc<T>),
+    /// The boxed object was moved back into the native library.
+    Moved,
+}
+
+/// A `Box<dyn Trait>` or `Arc<dyn Trait>` that was passed to foreign code.
+///
+/// Foreign code calls the trait methods through the functions exported for the trait
+/// and releases the object through the exported `__polygen_drop` function.
+/// Passing the handle back as a `Box` moves the object out of it,
+/// so any later method call through the same handle panics.
+#[repr(C)]
+pub struct DynHandle<T: ?Sized + 'static> {
+    ptr: *mut DynObject<T>,
+    drop: unsafe extern "C" fn(*mut DynObject<T>),
+}
+
+impl<T: ?Sized + 'static> DynHandle<T> {
+    pub const FIELDS: &'static [StructField] = &[
+        StructField {
+            visible: false,
+            readonly: false,
+            name: "ptr",
+            docs: "",
+            offset: offset_of!(Self, ptr),
+            ty: FieldType::Typed(&<usize as ExportedPolyStruct>::STRUCT),
+        },
+        StructField {
+            visible: false,
+            readonly: false,
+            name: "drop",
+            docs: "",
+            offset: offset_of!(Self, drop),
+            ty: FieldType::Typed(&<extern "C" fn(*mut c_void) as ExportedPolyStruct>::STRUCT),
+        },
+    ];
+
+    fn new(object: DynObject<T>) -> Self {
+        Self {
+            ptr: Box::into_raw(Box::new(object)),
+            drop: drop_box::<DynObject<T>>,
+        }
+    }
+
+    /// Returns the trait object behind the handle.
+    ///
+    /// # Safety
+    /// The handle must not have been released by foreign code.
+    pub unsafe fn object(&self) -> &T {
+        match &*self.ptr {
+            DynObject::Boxed(object) => object,
+            DynObject::Shared(object) => object,
+            DynObject::Moved => panic!("The trait object was moved out of its handle"),
+        }
+    }
+
+    /// Returns the trait object behind the handle mutably.
+    ///
+    /// Shared objects can only be borrowed mutably while the handle is their only owner.
+    ///
+    /// # Safety
+    /// The handle must not have been released by foreign code.
+    pub unsafe fn object_mut(&mut self) -> &mut T {
+        match &mut *self.ptr {
+            DynObject::Boxed(object) => object,
+            DynObject::Shared(object) => {
+                Arc::get_mut(object).expect("The shared trait object has other owners")
+            }
+            DynObject::Moved => panic!("The trait object was moved out of its handle"),
+        }
+    }
+
+    /// Moves a boxed trait object out of the handle.
+    ///
+    /// `From<DynHandle<dyn Trait>>` for `Box<dyn Trait>` is implemented by `#[polygen(export)]`
+    /// through this, as the orphan rule forbids a generic implementation for `Box`.
+    pub fn into_box(self) -> Box<T> {
+        // foreign code still owns the handle, so only the object is moved out of it
+        let object = unsafe { &mut *self.ptr };
+        match replace(object, DynObject::Moved) {
+            DynObject::Boxed(boxed) => boxed,
+            DynObject::Shared(shared) => {
+                *object = DynObject::Shared(shared);
+                panic!("A shared trait object can not be moved into a box")
+            }
+            DynObject::Moved => panic!("The trait object was moved out of its handle"),
+        }
+    }
+}
+
+impl<T: ?Sized + 'static> From<Box<T>> for DynHandle<T> {
+    fn from(value: Box<T>) -> Self {
+        Self::new(DynObject::Boxed(value))
+    }
+}
+
+impl<T: ?Sized + 'static> From<Arc<T>> for DynHandle<T> {
+    fn from(value: Arc<T>) -> Self {
+        Self::new(DynObject::Shared(value))
+    }
+}
+
+impl<T: ?Sized + 'static> From<DynHandle<T>> for Arc<T> {
+    fn from(value: DynHandle<T>) -> Self {
+        // boxed objects become shared, so the handle stays usable
+        let object = unsafe { &mut *value.ptr };
+        let shared = match replace(object, DynObject::Moved) {
+            DynObject::Boxed(boxed) => Arc::from(boxed),
+            DynObject::Shared(shared) => shared,
+            DynObject::Moved => panic!("The trait object was moved out of its handle"),
+        };
+
+        *object = DynObject::Shared(shared.clone());
+        shared
+    }
+}
+
+unsafe impl<T: ?Sized + ExportedPolyDyn> ExportedPolyStruct for Box<T>
+where
+    Box<T>: From<DynHandle<T>>,
+{
+    type ExportedType = DynHandle<T>;
+    const STRUCT: PolyType = T::STRUCT;
+    const CONSUMES_HANDLE: bool = true;
+}
+
+unsafe impl<T: ?Sized + ExportedPolyDyn> ExportedPolyStruct for Arc<T> {
+    type ExportedType = DynHandle<T>;
+    const STRUCT: PolyType = T::STRUCT;
+}
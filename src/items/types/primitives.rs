@@ -19,3 +19,27 @@ impl_item! {
     f32, f64,
     bool,
 }
+
+// void is only exported as the pointee of untyped pointers
+unsafe impl crate::__private::ExportedPolyStruct for std::ffi::c_void {
+    type ExportedType = std::ffi::c_void;
+    const STRUCT: crate::items::PolyType = crate::items::PolyType::Primitive("c_void");
+}
+
+// record constant values of every primitive
+macro_rules! impl_value {
+    ($variant:ident as $repr:ty: $($item:ty),+ $(,)?) => {
+        $(
+            impl $crate::__private::ExportedPolyValue for $item {
+                fn poly_value(self) -> $crate::items::PolyValue {
+                    $crate::items::PolyValue::$variant(self as $repr)
+                }
+            }
+        )+
+    };
+}
+
+impl_value!(UInt as u64: u8, u16, u32, u64, usize);
+impl_value!(Int as i64: i8, i16, i32, i64, isize);
+impl_value!(Float as f64: f32, f64);
+impl_value!(Bool as bool: bool);
@@ -1,11 +1,16 @@
 use crate::{__private::ExportedPolyStruct, items::PolyType};
 
+/// Pointees are resolved lazily so structs can point to themselves.
+fn pointee<T: ExportedPolyStruct>() -> PolyType {
+    T::STRUCT
+}
+
 unsafe impl<T: ExportedPolyStruct> ExportedPolyStruct for *mut T {
     type ExportedType = *mut T;
-    const STRUCT: PolyType = PolyType::Pointer(&T::STRUCT);
+    const STRUCT: PolyType = PolyType::Pointer(pointee::<T>);
 }
 
 unsafe impl<T: ExportedPolyStruct> ExportedPolyStruct for *const T {
     type ExportedType = *const T;
-    const STRUCT: PolyType = PolyType::Pointer(&T::STRUCT);
+    const STRUCT: PolyType = PolyType::Pointer(pointee::<T>);
 }
@@ -1,8 +1,15 @@
+mod abi;
 mod bag;
+mod embed;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
 
 #[doc(hidden)]
 pub mod __private;
+pub mod diff;
+pub mod ir;
 pub mod items;
+pub mod validate;
 
 pub use bag::*;
 
@@ -0,0 +1,352 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use heck::{ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase};
+use thiserror::Error;
+
+use crate::{
+    ir::{FieldType, FnParams, ItemKey, PolyStruct, PolyType},
+    PolyBag, PolyMod,
+};
+
+/// The primitives every generator knows how to render.
+const PRIMITIVES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize", "bool", "f32", "f64",
+];
+
+const CSHARP_KEYWORDS: &str =
+    "abstract as base bool break byte case catch char checked class const continue decimal \
+    default delegate do double else enum event explicit extern false finally fixed float for \
+    foreach goto if implicit in int interface internal is lock long namespace new null object \
+    operator out override params private protected public readonly ref return sbyte sealed \
+    short sizeof stackalloc static string struct switch this throw true try typeof uint ulong \
+    unchecked unsafe ushort using virtual void volatile while";
+
+const JS_KEYWORDS: &str =
+    "arguments await break case catch class const continue debugger default delete do else enum \
+    eval export extends false finally for function if implements import in instanceof \
+    interface let new null package private protected public return static super switch this \
+    throw true try typeof var void while with yield";
+
+/// Keywords of the C declarations the php bindings pass to `FFI::cdef`.
+const C_KEYWORDS: &str =
+    "_Bool auto bool break case char const continue default do double else enum extern float \
+    for goto if inline int long register restrict return short signed sizeof static struct \
+    switch typedef union unsigned void volatile while";
+
+/// Keywords and reserved class names of php, which are case insensitive.
+const PHP_KEYWORDS: &str =
+    "__halt_compiler abstract and array as bool break callable case catch class clone const \
+    continue declare default die do echo else elseif empty enddeclare endfor endforeach endif \
+    endswitch endwhile enum eval exit extends false final finally float fn for foreach function \
+    global goto if implements include include_once instanceof insteadof int interface isset \
+    iterable list match mixed namespace never new null object or parent print private \
+    protected public readonly require require_once return self static string switch throw \
+    trait true try unset use var void while xor yield";
+
+/// What a name is used for, which decides how each language renders it.
+#[derive(Clone, Copy)]
+enum NameKind {
+    /// A field or parameter.
+    Value,
+    Function,
+    Struct,
+    Constant,
+    Module,
+}
+
+/// A problem found by [`PolyBag::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The module path of the item.
+    pub module: String,
+    /// The name of the item, prefixed by its struct for methods and fields.
+    pub item: String,
+    pub kind: ProblemKind,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}::{}` {}", self.module, self.item, self.kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProblemKind {
+    #[error("collides with `{other}`, both are named `{converted}` in the bindings")]
+    NameCollision { other: String, converted: String },
+    #[error("uses `{name}`, which is a reserved keyword in {lang}")]
+    ReservedKeyword { name: String, lang: &'static str },
+    #[error("uses the unsupported type `{0}`")]
+    UnsupportedType(String),
+    #[error("uses the generic `{0}`, which is not defined")]
+    UndefinedGeneric(String),
+    #[error("is exported as `{0}`, which is already used by another item")]
+    DuplicateSymbol(String),
+    #[error("contains itself by value through {}", .0.join(" -> "))]
+    CyclicStruct(Vec<String>),
+    #[error("is defined by more than one impl block")]
+    DuplicateMethod,
+}
+
+impl ProblemKind {
+    /// Returns the only language whose bindings are affected, or `None` if it affects every language.
+    pub fn lang(&self) -> Option<&'static str> {
+        match self {
+            Self::ReservedKeyword { lang, .. } => Some(lang),
+            _ => None,
+        }
+    }
+}
+
+impl PolyBag {
+    /// Checks the bag for problems that would make a generator fail or render invalid bindings.
+    ///
+    /// Returns an empty list if the bag is valid.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut validator = Validator::default();
+        validator.validate_module(self.root_module(), self.root_module().name());
+        validator.problems
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    problems: Vec<Problem>,
+    symbols: HashMap<String, String>,
+}
+
+impl Validator {
+    fn push(&mut self, module: &str, item: impl Into<String>, kind: ProblemKind) {
+        let (module, item) = (module.to_string(), item.into());
+        self.problems.push(Problem { module, item, kind });
+    }
+
+    fn validate_module(&mut self, m: &PolyMod, parent: &str) {
+        // modules only know their name, so take the path from one of their items
+        let module = m.structs().map(|(s, _)| s.module.clone());
+        let module = module.chain(m.functions().map(|f| f.module.clone()));
+        let module = module.chain(m.constants().map(|c| c.module.clone())).next();
+        let module = module.unwrap_or_else(|| format!("{parent}::{}", m.name()));
+
+        let mut names = Names::default();
+        for m in m.modules() {
+            names.insert(self, &module, m.name(), m.name().to_pascal_case());
+            self.validate_identifier(&module, m.name(), m.name(), NameKind::Module);
+        }
+
+        for (s, i) in m.structs() {
+            // generic instances share their name
+            if !names.contains(&s.name) {
+                names.insert(self, &s.module, &s.name, s.name.to_pascal_case());
+                self.validate_identifier(&s.module, &s.name, &s.name, NameKind::Struct);
+            }
+
+            self.validate_struct(s);
+            let mut members = Names::default();
+            for f in &s.fields {
+                let item = format!("{}::{}", s.name, f.name);
+                members.insert(self, &s.module, &item, f.name.to_pascal_case());
+            }
+
+            // impl blocks are merged, so the same method can be defined twice
+            let mut methods = HashSet::new();
+            for f in i.iter().flat_map(|i| &i.functions) {
+                let item = format!("{}::{}", s.name, f.name);
+                match methods.insert(&f.name) {
+                    true => members.insert(self, &s.module, &item, f.name.to_pascal_case()),
+                    false => self.push(&s.module, &item, ProblemKind::DuplicateMethod),
+                }
+
+                self.validate_symbol(&s.module, &item, &f.export_name);
+                self.validate_params(&s.module, &item, &f.params);
+            }
+
+            for c in i.iter().flat_map(|i| &i.constants) {
+                let item = format!("{}::{}", s.name, c.name);
+                members.insert(self, &s.module, &item, c.name.to_pascal_case());
+                self.validate_identifier(&s.module, &item, &c.name, NameKind::Constant);
+                self.validate_type(&s.module, &item, &c.ty);
+            }
+        }
+
+        for c in m.constants() {
+            names.insert(self, &c.module, &c.name, c.name.to_pascal_case());
+            self.validate_identifier(&c.module, &c.name, &c.name, NameKind::Constant);
+            self.validate_type(&c.module, &c.name, &c.ty);
+        }
+
+        for f in m.functions() {
+            names.insert(self, &f.module, &f.name, f.name.to_pascal_case());
+            self.validate_identifier(&f.module, &f.name, &f.name, NameKind::Function);
+            self.validate_symbol(&f.module, &f.name, &f.export_name);
+            self.validate_params(&f.module, &f.name, &f.params);
+        }
+
+        for m in m.modules() {
+            self.validate_module(m, &module);
+        }
+    }
+
+    fn validate_struct(&mut self, s: &PolyStruct) {
+        for f in &s.fields {
+            let item = format!("{}::{}", s.name, f.name);
+            self.validate_identifier(&s.module, &item, &f.name, NameKind::Value);
+            match &f.ty {
+                FieldType::Typed(t) => self.validate_type(&s.module, &item, t),
+                FieldType::Generic(ident) => {
+                    if !s.generics.iter().any(|g| g.ident == *ident) {
+                        let kind = ProblemKind::UndefinedGeneric(ident.clone());
+                        self.push(&s.module, item, kind);
+                    }
+                }
+            }
+        }
+
+        for g in &s.generics {
+            self.validate_type(&s.module, &s.name, &g.ty);
+        }
+
+        let mut stack = vec![s.key()];
+        if let Some(cycle) = find_cycle(s, &mut stack) {
+            self.push(&s.module, &s.name, ProblemKind::CyclicStruct(cycle));
+        }
+    }
+
+    fn validate_params(&mut self, module: &str, item: &str, params: &FnParams) {
+        let mut names = Names::default();
+        for i in params.inputs.iter().filter(|i| i.name != "self") {
+            let input = format!("{item}::{}", i.name);
+            names.insert(self, module, &input, i.name.to_lower_camel_case());
+            self.validate_identifier(module, item, &i.name, NameKind::Value);
+            self.validate_type(module, item, &i.ty);
+        }
+
+        if let Some(t) = &params.output {
+            self.validate_type(module, item, t);
+        }
+    }
+
+    fn validate_symbol(&mut self, module: &str, item: &str, symbol: &str) {
+        let path = format!("{module}::{item}");
+        match self.symbols.get(symbol) {
+            Some(other) if *other != path => {
+                let kind = ProblemKind::DuplicateSymbol(symbol.to_string());
+                self.push(module, item, kind);
+            }
+            _ => {
+                self.symbols.insert(symbol.to_string(), path);
+            }
+        }
+    }
+
+    /// Checks a name against the keywords of every language that renders it where keywords are not allowed.
+    ///
+    /// Methods are not checked, since every language allows keywords as method names.
+    fn validate_identifier(&mut self, module: &str, item: &str, name: &str, kind: NameKind) {
+        let camel = name.to_lower_camel_case();
+        let rendered = match kind {
+            NameKind::Value => vec![
+                ("C#", CSHARP_KEYWORDS, camel.clone()),
+                ("javascript", JS_KEYWORDS, camel.clone()),
+                ("C", C_KEYWORDS, name.to_string()),
+                // php variables start with `$`, which leaves only `$this` reserved
+                ("PHP", "this", camel),
+            ],
+            // javascript and c# render the other names as members or in pascal case
+            NameKind::Function => vec![("PHP", PHP_KEYWORDS, camel)],
+            NameKind::Struct | NameKind::Module => {
+                vec![("PHP", PHP_KEYWORDS, name.to_pascal_case())]
+            }
+            NameKind::Constant => vec![("PHP", PHP_KEYWORDS, name.to_shouty_snake_case())],
+        };
+
+        for (lang, keywords, name) in rendered {
+            let is_keyword = |k: &str| match lang {
+                "PHP" => k.eq_ignore_ascii_case(&name),
+                _ => k == name,
+            };
+            if keywords.split_whitespace().any(is_keyword) {
+                self.push(module, item, ProblemKind::ReservedKeyword { name, lang });
+            }
+        }
+    }
+
+    fn validate_type(&mut self, module: &str, item: &str, t: &PolyType) {
+        match t {
+            PolyType::Primitive(p) if !PRIMITIVES.contains(&p.as_str()) => {
+                self.push(module, item, ProblemKind::UnsupportedType(p.clone()));
+            }
+            PolyType::Primitive(_) | PolyType::Struct(_) => (),
+            // void can only be referred to through a pointer
+            PolyType::Pointer(t) if matches!(&**t, PolyType::Primitive(p) if p == "c_void") => (),
+            PolyType::Pointer(t) => self.validate_type(module, item, t),
+            PolyType::FnPtr(f) => {
+                for t in f.inputs.iter().chain(f.output.as_deref()) {
+                    self.validate_type(module, item, t);
+                }
+            }
+        }
+    }
+}
+
+/// The names in a single scope of the bindings, after case conversion.
+#[derive(Default)]
+struct Names {
+    converted: HashMap<String, String>,
+}
+
+impl Names {
+    fn contains(&self, name: &str) -> bool {
+        self.converted.values().any(|n| n == name)
+    }
+
+    fn insert(&mut self, validator: &mut Validator, module: &str, item: &str, converted: String) {
+        match self.converted.get(&converted) {
+            Some(other) => {
+                let other = other.clone();
+                let kind = ProblemKind::NameCollision { other, converted };
+                validator.push(module, item, kind);
+            }
+            None => {
+                let name = item.rsplit("::").next().unwrap_or(item);
+                self.converted.insert(converted, name.to_string());
+            }
+        }
+    }
+}
+
+/// Follows every by-value struct field and returns the path back to a struct on the stack.
+fn find_cycle(s: &PolyStruct, stack: &mut Vec<ItemKey>) -> Option<Vec<String>> {
+    for f in &s.fields {
+        let ty = match &f.ty {
+            FieldType::Typed(t) => t,
+            FieldType::Generic(ident) => match s.generics.iter().find(|g| g.ident == *ident) {
+                Some(g) => &g.ty,
+                None => continue,
+            },
+        };
+
+        // pointers break the cycle
+        let PolyType::Struct(inner) = ty else {
+            continue;
+        };
+
+        let key = inner.key();
+        if stack.contains(&key) {
+            let cycle = stack.iter().chain([&key]).map(ToString::to_string);
+            return Some(cycle.collect());
+        }
+
+        stack.push(key);
+        if let Some(cycle) = find_cycle(inner, stack) {
+            return Some(cycle);
+        }
+
+        stack.pop();
+    }
+
+    None
+}
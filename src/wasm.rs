@@ -0,0 +1,37 @@
+//! Memory helpers exported from wasm libraries.
+//!
+//! Exported functions on wasm take all of their arguments by pointer.
+//! These helpers let the host allocate that memory inside of the wasm instance.
+
+use std::alloc::Layout;
+
+/// Allocates `size` bytes aligned to `align` inside of the wasm memory.
+///
+/// Returns a null pointer if the layout is invalid or the allocation fails.
+#[no_mangle]
+pub extern "C" fn __polygen_alloc(size: usize, align: usize) -> *mut u8 {
+    let Ok(layout) = Layout::from_size_align(size, align) else {
+        return std::ptr::null_mut();
+    };
+
+    match layout.size() {
+        0 => align as *mut u8, // zero sized allocations only need to be aligned
+        _ => unsafe { std::alloc::alloc(layout) },
+    }
+}
+
+/// Frees memory previously allocated with [`__polygen_alloc`].
+///
+/// This may also be used to free the memory owned by a `PolyBox`.
+#[no_mangle]
+pub unsafe extern "C" fn __polygen_free(ptr: *mut u8, size: usize, align: usize) {
+    let Ok(layout) = Layout::from_size_align(size, align) else {
+        return;
+    };
+
+    if ptr.is_null() || layout.size() == 0 {
+        return;
+    }
+
+    std::alloc::dealloc(ptr, layout);
+}